@@ -0,0 +1,261 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitStr, Token};
+
+/// Generates an associated constant per field holding its typed path, e.g.
+/// `Cat::CREATED_AT: Field<Cat, Date>`. Field names follow
+/// `#[serde(rename = "...")]`, so `id` maps to `_id`, and the container's
+/// `#[serde(rename_all = "...")]`. Fields with `#[serde(skip)]` get no constant,
+/// `#[serde(flatten)]` is rejected since the nested fields have no path of
+/// their own.
+///
+/// The constants are used with the builders in `utils::query`, a misspelled
+/// field or a value of the wrong type is a compile error.
+#[proc_macro_derive(Fields)]
+pub fn derive_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let model = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    model,
+                    "Fields can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                model,
+                "Fields can only be derived for structs",
+            ))
+        }
+    };
+
+    let container = serde_attrs(&input.attrs)?;
+
+    let mut constants = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let attrs = serde_attrs(&field.attrs)?;
+        if attrs.flatten {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "Fields can't be derived for structs with flattened fields",
+            ));
+        }
+        if attrs.skip {
+            continue;
+        }
+
+        let ty = &field.ty;
+        let name = match (attrs.rename, &container.rename_all) {
+            (Some(rename), _) => rename,
+            (None, Some(rule)) => rename_all(rule, &ident.unraw().to_string())?,
+            (None, None) => ident.unraw().to_string(),
+        };
+        let constant = format_ident!("{}", ident.unraw().to_string().to_uppercase());
+        let doc = format!("Path of the `{}` field, stored as `{}`.", ident, name);
+
+        constants.push(quote! {
+            #[doc = #doc]
+            pub const #constant: crate::utils::query::Field<#model, #ty> =
+                crate::utils::query::Field::new(#name);
+        });
+    }
+
+    Ok(quote! {
+        #[allow(dead_code)]
+        impl #model {
+            #(#constants)*
+        }
+    })
+}
+
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<LitStr>,
+    skip: bool,
+    flatten: bool,
+}
+
+// Reads the serde attributes that decide how a field is stored, the others are
+// skipped.
+fn serde_attrs(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut serde = SerdeAttrs::default();
+
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                serde.rename = serialized_name(&meta)?.map(|name| name.value());
+            } else if meta.path.is_ident("rename_all") {
+                serde.rename_all = serialized_name(&meta)?;
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                serde.skip = true;
+            } else if meta.path.is_ident("flatten") {
+                serde.flatten = true;
+            } else if meta.input.peek(Token![=]) {
+                // Skip the value of any other attribute, e.g. `default = "..."`.
+                let _: syn::Expr = meta.value()?.parse()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                meta.parse_nested_meta(|nested| {
+                    if nested.input.peek(Token![=]) {
+                        let _: syn::Expr = nested.value()?.parse()?;
+                    }
+                    Ok(())
+                })?;
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(serde)
+}
+
+// Documents are stored serialized, so `rename(serialize = "...")` is the name
+// that counts.
+fn serialized_name(meta: &ParseNestedMeta) -> syn::Result<Option<LitStr>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse()?));
+    }
+
+    let mut name = None;
+    meta.parse_nested_meta(|nested| {
+        let value: LitStr = nested.value()?.parse()?;
+        if nested.path.is_ident("serialize") {
+            name = Some(value);
+        }
+        Ok(())
+    })?;
+
+    Ok(name)
+}
+
+// The `rename_all` rules serde supports, applied to a snake case field name.
+fn rename_all(rule: &LitStr, field: &str) -> syn::Result<String> {
+    let pascal = || {
+        field
+            .split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                    .unwrap_or_default()
+            })
+            .collect::<String>()
+    };
+
+    Ok(match rule.value().as_str() {
+        "lowercase" | "snake_case" => field.to_owned(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|first| first.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.to_uppercase().replace('_', "-"),
+        other => {
+            return Err(syn::Error::new_spanned(
+                rule,
+                format!("unknown rename_all rule `{}`", other),
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn names(input: DeriveInput) -> Vec<String> {
+        let tokens = expand(input).unwrap().to_string();
+        tokens
+            .split("Field :: new")
+            .skip(1)
+            .map(|rest| rest.split('"').nth(1).unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn fields_follow_serde_renames() {
+        let input = parse_quote! {
+            #[serde(rename_all = "camelCase")]
+            struct Cat {
+                #[serde(rename = "_id")]
+                id: Option<ObjectId>,
+                created_at: Date,
+                #[serde(rename(serialize = "owner", deserialize = "user"))]
+                user: ObjectId,
+                r#type: String,
+            }
+        };
+
+        assert_eq!(names(input), vec!["_id", "createdAt", "owner", "type"]);
+    }
+
+    #[test]
+    fn skipped_fields_have_no_path() {
+        let input = parse_quote! {
+            struct Cat {
+                name: String,
+                #[serde(skip)]
+                cache: Vec<u8>,
+                #[serde(skip_serializing)]
+                password: String,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                notes: Option<String>,
+            }
+        };
+
+        assert_eq!(names(input), vec!["name", "notes"]);
+    }
+
+    #[test]
+    fn flattened_fields_and_unknown_rules_are_rejected() {
+        let input = parse_quote! {
+            struct Cat {
+                #[serde(flatten)]
+                details: Details,
+            }
+        };
+        let err = expand(input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Fields can't be derived for structs with flattened fields"
+        );
+
+        let input = parse_quote! {
+            #[serde(rename_all = "Title Case")]
+            struct Cat {
+                name: String,
+            }
+        };
+        let err = expand(input).unwrap_err();
+        assert_eq!(err.to_string(), "unknown rename_all rule `Title Case`");
+    }
+}
@@ -1,45 +1,42 @@
 use axum::http::header;
-use axum::{Router};
+use axum::Router;
+use std::time::Duration;
 use tower_http::{
-    compression::CompressionLayer, cors::CorsLayer, propagate_header::PropagateHeaderLayer,
+    compression::CompressionLayer, propagate_header::PropagateHeaderLayer,
     sensitive_headers::SetSensitiveHeadersLayer, trace,
 };
 
-use crate::logger;
+use crate::database;
+use crate::migrations;
 use crate::models;
+use crate::purge;
 use crate::routes;
+use crate::state::AppState;
 
-fn env_truthy(key: &str) -> bool {
-    std::env::var(key)
-        .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE" | "yes" | "YES"))
-        .unwrap_or(false)
-}
-
-pub async fn create_app() -> Router {
-    logger::setup();
-
-    // ✅ default: no DB. Enable DB with USE_DB=1
-    let use_db = env_truthy("USE_DB");
-    let skip_db = !use_db;
-
+pub fn create_app(state: AppState) -> Router {
     let mut app = Router::new()
         .merge(routes::public::create_route())
         .merge(routes::status::create_route())
         .merge(Router::new().nest("/v1", Router::new()));
 
-    if skip_db {
+    if !state.settings.database.enabled {
         tracing::warn!("🟡 DB disabled (set USE_DB=1 to enable). Skipping Mongo init + DB routes");
     } else {
-        if let Err(e) = models::sync_indexes().await {
-            tracing::error!(error=%e, "🔴 DB init failed; continuing without DB routes (set USE_DB=0 to skip)");
-        } else {
-            app = app
-                .merge(routes::user::create_route())
-                .merge(Router::new().nest(
-                    "/v1",
-                    Router::new().merge(routes::cat::create_route()),
-                ));
-        }
+        // The routes answer 503 until the database is initialized.
+        tokio::spawn(init_db(state.clone()));
+        purge::spawn(state.clone());
+
+        app = app.merge(routes::user::create_route()).merge(
+            Router::new().nest(
+                "/v1",
+                Router::new()
+                    .merge(routes::cat::create_route())
+                    .merge(routes::cat_event::create_route())
+                    .merge(routes::cat_transfer::create_route())
+                    .merge(routes::household::create_route())
+                    .merge(routes::photo::create_route(&state.settings.photos)),
+            ),
+        );
     }
 
     app.layer(
@@ -48,8 +45,51 @@ pub async fn create_app() -> Router {
             .on_request(trace::DefaultOnRequest::new().level(tracing::Level::INFO))
             .on_response(trace::DefaultOnResponse::new().level(tracing::Level::INFO)),
     )
-    .layer(SetSensitiveHeadersLayer::new(std::iter::once(header::AUTHORIZATION)))
+    .layer(SetSensitiveHeadersLayer::new(std::iter::once(
+        header::AUTHORIZATION,
+    )))
     .layer(CompressionLayer::new())
-    .layer(PropagateHeaderLayer::new(header::HeaderName::from_static("x-request-id")))
-    .layer(CorsLayer::permissive())
+    .layer(PropagateHeaderLayer::new(header::HeaderName::from_static(
+        "x-request-id",
+    )))
+    .layer(state.allowed_origins.layer())
+    .with_state(state)
+}
+
+// Keeps retrying while MongoDB is unreachable, so the DB routes recover
+// without a restart when it comes back. Errors that would fail the same way on
+// every attempt, like drifted indexes with `fail_on_drift` or a locked
+// migration, mark the database as failed and `cli::serve` exits.
+async fn init_db(state: AppState) {
+    let mut backoff = Duration::from_millis(state.settings.database.retry_backoff_ms);
+
+    loop {
+        match try_init_db(&state).await {
+            Ok(()) => {
+                state.db_ready.set_ready();
+                tracing::info!("🟢 DB initialized, DB routes are available");
+                return;
+            }
+            Err(e) if database::is_transient(&e) => {
+                tracing::error!(error=%e, "🔴 DB init failed; DB routes answer 503, retrying in {:?}", backoff);
+            }
+            Err(e) => {
+                tracing::error!(error=%e, "🔴 DB init failed and retrying won't help");
+                state.db_ready.set_failed(e.to_string());
+                return;
+            }
+        }
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(database::MAX_BACKOFF);
+    }
+}
+
+async fn try_init_db(state: &AppState) -> Result<(), crate::errors::Error> {
+    if state.settings.database.migrate_on_boot {
+        let steps = migrations::up(&state.db, false).await?;
+        tracing::info!("Applied {} pending migrations", steps.len());
+    }
+
+    models::sync_indexes(&state.db, &state.settings.database.indexes).await
 }
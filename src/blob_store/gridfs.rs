@@ -0,0 +1,97 @@
+use axum::async_trait;
+use bytes::Bytes;
+use futures::stream::TryStreamExt;
+use wither::bson::doc;
+use wither::mongodb::gridfs::GridFsBucket;
+use wither::mongodb::options::{GridFsBucketOptions, GridFsFindOptions, GridFsUploadOptions};
+use wither::mongodb::Database;
+
+use super::{check_key, BlobStore};
+use crate::errors::BlobStoreError;
+
+/// Stores blobs in a GridFS bucket, using the key as the file name.
+pub struct GridFsStore {
+    bucket: GridFsBucket,
+}
+
+impl GridFsStore {
+    pub fn new(db: &Database, bucket: &str) -> Self {
+        let options = GridFsBucketOptions::builder()
+            .bucket_name(bucket.to_owned())
+            .build();
+
+        Self {
+            bucket: db.gridfs_bucket(options),
+        }
+    }
+}
+
+#[async_trait]
+impl BlobStore for GridFsStore {
+    async fn put(&self, key: &str, bytes: Bytes, content_type: &str) -> Result<(), BlobStoreError> {
+        check_key(key)?;
+
+        let options = GridFsUploadOptions::builder()
+            .metadata(doc! { "contentType": content_type })
+            .build();
+        let id = self
+            .bucket
+            .upload_from_futures_0_3_reader(key, &bytes[..], options)
+            .await?;
+
+        // GridFS keeps every revision of a file name, only the new one is kept.
+        let previous = self
+            .bucket
+            .find(doc! { "filename": key, "_id": { "$ne": id } }, None)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        for file in previous {
+            self.bucket.delete(file.id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, BlobStoreError> {
+        check_key(key)?;
+
+        let options = GridFsFindOptions::builder()
+            .sort(doc! { "uploadDate": -1 })
+            .limit(1)
+            .build();
+        let file = self
+            .bucket
+            .find(doc! { "filename": key }, options)
+            .await?
+            .try_next()
+            .await?;
+
+        let Some(file) = file else {
+            return Ok(None);
+        };
+
+        let mut bytes = Vec::new();
+        self.bucket
+            .download_to_futures_0_3_writer(file.id, &mut bytes)
+            .await?;
+
+        Ok(Some(bytes.into()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), BlobStoreError> {
+        check_key(key)?;
+
+        let files = self
+            .bucket
+            .find(doc! { "filename": key }, None)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        for file in files {
+            self.bucket.delete(file.id).await?;
+        }
+
+        Ok(())
+    }
+}
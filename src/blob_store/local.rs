@@ -0,0 +1,65 @@
+use axum::async_trait;
+use bytes::Bytes;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tokio::fs;
+use wither::bson::oid::ObjectId;
+
+use super::{check_key, BlobStore};
+use crate::errors::BlobStoreError;
+
+/// Stores each blob as a file under `root`, at the path given by its key.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, BlobStoreError> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalStore {
+    async fn put(
+        &self,
+        key: &str,
+        bytes: Bytes,
+        _content_type: &str,
+    ) -> Result<(), BlobStoreError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        // Readers never see a partially written blob.
+        let tmp = path.with_file_name(format!(".{}.tmp", ObjectId::new().to_hex()));
+        fs::write(&tmp, &bytes).await?;
+        if let Err(err) = fs::rename(&tmp, &path).await {
+            let _ = fs::remove_file(&tmp).await;
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, BlobStoreError> {
+        match fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes.into())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), BlobStoreError> {
+        match fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use axum::async_trait;
+use bytes::Bytes;
+use std::sync::Arc;
+use wither::mongodb::Database;
+
+use crate::errors::BlobStoreError;
+use crate::settings::Storage;
+
+mod gridfs;
+mod local;
+mod s3;
+
+pub use gridfs::GridFsStore;
+pub use local::LocalStore;
+pub use s3::S3Store;
+
+/// Stores opaque blobs by key. Keys are `/` separated paths made of ASCII
+/// letters, digits, `-`, `_` and `.`, e.g. `photos/<id>/thumbnail`.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Stores the blob, replacing any blob with the same key.
+    async fn put(&self, key: &str, bytes: Bytes, content_type: &str) -> Result<(), BlobStoreError>;
+
+    /// The blob, or `None` when there is no blob with that key.
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, BlobStoreError>;
+
+    /// Deletes the blob. Deleting a missing blob is not an error.
+    async fn delete(&self, key: &str) -> Result<(), BlobStoreError>;
+}
+
+/// The store configured in `photos.storage`.
+pub fn from_settings(storage: &Storage, db: &Database) -> Arc<dyn BlobStore> {
+    match storage {
+        Storage::Local { path } => Arc::new(LocalStore::new(path.clone())),
+        Storage::Gridfs { bucket } => Arc::new(GridFsStore::new(db, bucket)),
+        Storage::S3 {
+            endpoint,
+            bucket,
+            region,
+            access_key_id,
+            secret_access_key,
+        } => Arc::new(S3Store::new(
+            endpoint,
+            bucket,
+            region,
+            access_key_id,
+            secret_access_key.clone(),
+        )),
+    }
+}
+
+// Keys end up in file paths and URLs, they are restricted so they can't escape
+// the store's root or need escaping.
+fn check_key(key: &str) -> Result<(), BlobStoreError> {
+    let valid_segment = |segment: &str| {
+        !segment.is_empty()
+            && segment != "."
+            && segment != ".."
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+
+    if key.split('/').all(valid_segment) {
+        Ok(())
+    } else {
+        Err(BlobStoreError::InvalidKey(key.to_owned()))
+    }
+}
@@ -0,0 +1,170 @@
+use axum::async_trait;
+use axum::http::{Method, StatusCode};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use super::{check_key, BlobStore};
+use crate::errors::BlobStoreError;
+use crate::settings::Secret;
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Stores blobs in an S3 compatible bucket. Requests are signed with AWS
+/// Signature Version 4 and use path style URLs, `<endpoint>/<bucket>/<key>`,
+/// which every S3 compatible service supports. The endpoint may have a path,
+/// e.g. `https://example.com/s3`.
+pub struct S3Store {
+    client: reqwest::Client,
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: Secret,
+}
+
+impl S3Store {
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: Secret,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_owned(),
+            bucket: bucket.to_owned(),
+            region: region.to_owned(),
+            access_key_id: access_key_id.to_owned(),
+            secret_access_key,
+        }
+    }
+
+    // Keys are checked to only contain characters that don't need escaping.
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Bytes,
+        content_type: Option<&str>,
+    ) -> Result<reqwest::Response, BlobStoreError> {
+        check_key(key)?;
+
+        let url = reqwest::Url::parse(&format!("{}/{}/{}", self.endpoint, self.bucket, key))
+            .map_err(|_| BlobStoreError::InvalidKey(key.to_owned()))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => return Err(BlobStoreError::InvalidKey(key.to_owned())),
+        };
+
+        let now = Utc::now();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        // The path includes the endpoint's own, e.g. `/s3` behind a proxy.
+        let authorization = self.authorization(&method, url.path(), "", &host, &payload_hash, now);
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("authorization", authorization)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", now.format(TIMESTAMP_FORMAT).to_string())
+            .body(body);
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+
+        Ok(request.send().await?)
+    }
+
+    /// The `authorization` header of a request signed at `now`, which must
+    /// send the same `x-amz-date` and `x-amz-content-sha256` headers. The
+    /// store's own requests have no query string.
+    pub fn authorization(
+        &self,
+        method: &Method,
+        path: &str,
+        query: &str,
+        host: &str,
+        payload_hash: &str,
+        now: DateTime<Utc>,
+    ) -> String {
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format(TIMESTAMP_FORMAT).to_string();
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, query, host, payload_hash, timestamp, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let secret = format!("AWS4{}", self.secret_access_key.expose());
+        let key = [date.as_str(), self.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(secret.into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+        let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        )
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3Store {
+    async fn put(&self, key: &str, bytes: Bytes, content_type: &str) -> Result<(), BlobStoreError> {
+        let res = self
+            .send(Method::PUT, key, bytes, Some(content_type))
+            .await?;
+        check_status(res).await?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, BlobStoreError> {
+        let res = self.send(Method::GET, key, Bytes::new(), None).await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(check_status(res).await?.bytes().await?))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), BlobStoreError> {
+        let res = self.send(Method::DELETE, key, Bytes::new(), None).await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+
+        check_status(res).await?;
+
+        Ok(())
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+async fn check_status(res: reqwest::Response) -> Result<reqwest::Response, BlobStoreError> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+
+    Err(BlobStoreError::Status {
+        status: res.status().as_u16(),
+        body: res.text().await.unwrap_or_default(),
+    })
+}
@@ -0,0 +1,338 @@
+use clap::{Args, Parser, Subcommand};
+use std::error::Error as StdError;
+use std::path::{Path, PathBuf};
+use wither::mongodb::Database;
+
+use crate::app;
+use crate::database;
+use crate::logger;
+use crate::migrations::{self, Step};
+use crate::models;
+use crate::models::user::{self, User};
+use crate::reload::Reloader;
+use crate::server;
+use crate::settings::{self, IndexMode, Settings, SettingsError};
+use crate::state::AppState;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::query::{Filter, Update};
+use crate::utils::token::{self, Keys};
+
+type CliResult = Result<(), Box<dyn StdError>>;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "rustapi",
+    version,
+    about = "RESTful API built with Axum and MongoDB"
+)]
+pub struct Cli {
+    /// Directory containing the configuration files.
+    #[arg(long, global = true, env = "CONFIG_DIR", default_value = settings::DEFAULT_CONFIG_DIR)]
+    pub config_dir: PathBuf,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the HTTP server. This is the default command.
+    Serve(ServeArgs),
+    /// Sync the indexes declared on the models without starting the server.
+    SyncIndexes(SyncIndexesArgs),
+    /// Create a user.
+    CreateUser(CreateUserArgs),
+    /// Lock a user, preventing them from authenticating.
+    LockUser(UserArgs),
+    /// Unlock a previously locked user.
+    UnlockUser(UserArgs),
+    /// Apply, revert or list the database migrations.
+    Migrate(MigrateArgs),
+    /// Authentication token utilities.
+    #[command(subcommand)]
+    Token(TokenCommand),
+    /// Configuration utilities.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Debug, Default, Args)]
+pub struct ServeArgs {
+    /// Port to listen on, overrides `server.port`.
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Host to bind to, overrides `server.host`.
+    #[arg(long)]
+    pub host: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SyncIndexesArgs {
+    /// Overrides `database.indexes.mode`.
+    #[arg(long, value_enum)]
+    pub mode: Option<IndexMode>,
+}
+
+#[derive(Debug, Args)]
+pub struct CreateUserArgs {
+    #[arg(long)]
+    pub name: String,
+
+    #[arg(long)]
+    pub email: String,
+
+    /// Prefer the environment variable, arguments are visible to other
+    /// users of the machine.
+    #[arg(long, env = "RUSTAPI_PASSWORD", hide_env_values = true)]
+    pub password: String,
+}
+
+#[derive(Debug, Args)]
+pub struct UserArgs {
+    #[arg(long)]
+    pub email: String,
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    #[command(subcommand)]
+    pub command: Option<MigrateCommand>,
+
+    /// Print the planned operations without running them.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply every pending migration. This is the default command.
+    Up,
+    /// Revert the applied migrations newer than the given version.
+    Down {
+        #[arg(long)]
+        to: u32,
+    },
+    /// List the migrations and whether they are applied.
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TokenCommand {
+    /// Issue an authentication token for a user, useful for debugging.
+    Issue(UserArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration, with secrets redacted, and validate it.
+    Check,
+}
+
+pub async fn run(cli: Cli) -> CliResult {
+    let config_dir = cli.config_dir;
+    let run_mode = settings::run_mode();
+    let load = || Settings::from_dir(&config_dir, &run_mode);
+
+    match cli.command.unwrap_or(Command::Serve(ServeArgs::default())) {
+        Command::Serve(args) => serve(load()?, args, config_dir.clone(), run_mode.clone()).await,
+        Command::SyncIndexes(args) => sync_indexes(&load()?, args).await,
+        Command::CreateUser(args) => create_user(&load()?, args).await,
+        Command::LockUser(args) => set_locked(&load()?, &args.email, true).await,
+        Command::UnlockUser(args) => set_locked(&load()?, &args.email, false).await,
+        Command::Migrate(args) => migrate(&load()?, args).await,
+        Command::Token(TokenCommand::Issue(args)) => issue_token(&load()?, &args.email).await,
+        Command::Config(ConfigCommand::Check) => check_config(&config_dir, &run_mode),
+    }
+}
+
+async fn serve(
+    settings: Settings,
+    args: ServeArgs,
+    config_dir: PathBuf,
+    run_mode: String,
+) -> CliResult {
+    let logger = logger::setup(&settings.logger);
+
+    // The reloader compares against the settings from the config files, the
+    // command line overrides below would otherwise show up as changes.
+    let mut overridden = settings.clone();
+    if let Some(port) = args.port {
+        overridden.server.port = port;
+    }
+    if let Some(host) = args.host {
+        overridden.server.host = host;
+    }
+
+    let state = AppState::new(overridden).await?;
+    Reloader::new(
+        config_dir,
+        run_mode,
+        settings,
+        logger,
+        state.allowed_origins.clone(),
+    )
+    .spawn()?;
+
+    let listener = server::Listener::bind(&state.settings.server).await?;
+    let db_ready = state.db_ready.clone();
+    let app = app::create_app(state);
+
+    // Use println! here to ensure you see this in logs
+    // even if tracing is not fully initialized yet
+    println!("🚀 Server started on {}", listener);
+
+    tokio::select! {
+        result = server::serve(listener, app) => result?,
+        message = db_ready.failed() => {
+            return Err(format!("Database initialization failed: {}", message).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn check_config(config_dir: &Path, run_mode: &str) -> CliResult {
+    let (settings, config) = Settings::load(config_dir, run_mode)?;
+
+    let redacted = serde_json::to_string_pretty(&settings.redacted())?;
+    println!("{}", redacted);
+
+    let problems = settings.problems(&config);
+    if !problems.is_empty() {
+        return Err(SettingsError::Invalid(problems).into());
+    }
+
+    println!("Configuration is valid");
+    Ok(())
+}
+
+async fn sync_indexes(settings: &Settings, args: SyncIndexesArgs) -> CliResult {
+    let db = database::connect(&settings.database).await?;
+
+    let mut indexes = settings.database.indexes.clone();
+    if let Some(mode) = args.mode {
+        indexes.mode = mode;
+    }
+
+    if indexes.mode == IndexMode::Verify {
+        let drifts = models::index_drift(&db).await?;
+        for drift in &drifts {
+            println!("{}", drift);
+        }
+
+        if !drifts.is_empty() {
+            return Err("Indexes drifted from the models".into());
+        }
+
+        println!("Indexes match the models");
+        return Ok(());
+    }
+
+    models::sync_indexes(&db, &indexes).await?;
+    println!("Indexes synced");
+
+    Ok(())
+}
+
+async fn migrate(settings: &Settings, args: MigrateArgs) -> CliResult {
+    let db = database::connect(&settings.database).await?;
+    let dry_run = args.dry_run;
+
+    match args.command.unwrap_or(MigrateCommand::Up) {
+        MigrateCommand::Up => {
+            let steps = migrations::up(&db, dry_run).await?;
+            print_steps(&steps, dry_run, "apply");
+        }
+        MigrateCommand::Down { to } => {
+            let steps = migrations::down(&db, to, dry_run).await?;
+            print_steps(&steps, dry_run, "revert");
+        }
+        MigrateCommand::Status => {
+            for step in migrations::status(&db).await? {
+                let state = match step.applied_at {
+                    Some(applied_at) => format!("applied {}", applied_at),
+                    None => "pending".to_owned(),
+                };
+                println!("{:04} {} ({})", step.version, step.name, state);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_steps(steps: &[Step], dry_run: bool, action: &str) {
+    if steps.is_empty() {
+        println!("No migrations to {}", action);
+        return;
+    }
+
+    for step in steps {
+        println!("{:04} {}", step.version, step.name);
+        if dry_run {
+            for operation in &step.plan {
+                println!("  {}", operation);
+            }
+        }
+    }
+
+    if dry_run {
+        println!("Dry run, nothing was changed");
+    }
+}
+
+pub async fn create_user(settings: &Settings, args: CreateUserArgs) -> CliResult {
+    let db = database::connect(&settings.database).await?;
+
+    let password_hash = user::hash_password(args.password).await?;
+    let user = User::new(args.name, args.email, password_hash);
+
+    let user = User::create(&db, user).await?;
+    println!("Created user {} ({})", user.email, user.id.unwrap());
+
+    Ok(())
+}
+
+pub async fn set_locked(settings: &Settings, email: &str, locked: bool) -> CliResult {
+    let db = database::connect(&settings.database).await?;
+
+    let now = date::now();
+    let locked_at = if locked { Some(now) } else { None };
+    let result = User::update_one(
+        &db,
+        Filter::new().eq(User::EMAIL, email),
+        Update::new()
+            .set(User::LOCKED_AT, locked_at)
+            .set(User::UPDATED_AT, now),
+        None,
+    )
+    .await?;
+
+    if result.matched_count == 0 {
+        return Err(format!("No user found with email {}", email).into());
+    }
+
+    let action = if locked { "Locked" } else { "Unlocked" };
+    println!("{} user {}", action, email);
+
+    Ok(())
+}
+
+async fn issue_token(settings: &Settings, email: &str) -> CliResult {
+    let db = database::connect(&settings.database).await?;
+    let user = find_user(&db, email).await?;
+
+    let keys = Keys::new(settings.auth.secret.expose().as_bytes());
+    let token = token::create(user, &keys)?;
+    println!("{}", token);
+
+    Ok(())
+}
+
+async fn find_user(db: &Database, email: &str) -> Result<User, Box<dyn StdError>> {
+    User::find_one(db, Filter::new().eq(User::EMAIL, email), None)
+        .await?
+        .ok_or_else(|| format!("No user found with email {}", email).into())
+}
@@ -0,0 +1,39 @@
+use axum::http::HeaderValue;
+use std::sync::{Arc, PoisonError, RwLock};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// The origins allowed by the CORS layer. They can be replaced at runtime,
+/// the layer checks the current list on every request.
+#[derive(Clone, Default)]
+pub struct AllowedOrigins(Arc<RwLock<Vec<HeaderValue>>>);
+
+impl AllowedOrigins {
+    pub fn new(origins: &[String]) -> Self {
+        let allowed = Self::default();
+        allowed.set(origins);
+        allowed
+    }
+
+    pub fn set(&self, origins: &[String]) {
+        // Invalid origins are reported by the settings validation.
+        let origins = origins
+            .iter()
+            .filter_map(|origin| HeaderValue::from_str(origin).ok())
+            .collect();
+
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = origins;
+    }
+
+    fn allows(&self, origin: &HeaderValue) -> bool {
+        let origins = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        origins.is_empty() || origins.contains(origin)
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let allowed = self.clone();
+
+        CorsLayer::permissive().allow_origin(AllowOrigin::predicate(move |origin, _| {
+            allowed.allows(origin)
+        }))
+    }
+}
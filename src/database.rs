@@ -1,21 +1,316 @@
-use mongodb::Database;
-use tokio::sync::OnceCell;
+use axum::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::request::Parts;
+use futures::future::BoxFuture;
+use mongodb::bson::doc;
+use mongodb::error::{ErrorKind, TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
+use mongodb::options::{
+    Acknowledgment, ClientOptions, ReadConcern, ReadPreference, ReadPreferenceOptions,
+    SelectionCriteria, Tls, TlsOptions, WriteConcern,
+};
+use mongodb::{Client, ClientSession, Database};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::warn;
 use wither::mongodb;
+use wither::WitherError;
 
-use crate::settings::SETTINGS;
+use crate::errors::{DatabaseError, Error};
+use crate::settings::{self, ReadConcernLevel, ReadPreferenceMode};
 
-static CONNECTION: OnceCell<Database> = OnceCell::const_new();
+// Retries back off exponentially up to this delay.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(10);
 
-pub async fn connection() -> &'static Database {
-    CONNECTION
-        .get_or_init(|| async {
-            let db_uri = SETTINGS.database.uri.as_str();
-            let db_name = SETTINGS.database.name.as_str();
+// Transactions failing with transient errors are retried until this much time
+// has passed since the first attempt, as the drivers' convenient API does.
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(120);
 
-            mongodb::Client::with_uri_str(db_uri)
-                .await
-                .expect("Failed to initialize MongoDB connection")
-                .database(db_name)
-        })
+// Server error codes of a node that is unreachable, shutting down or not the
+// primary anymore: HostUnreachable, HostNotFound, NetworkTimeout,
+// ShutdownInProgress, PrimarySteppedDown, InterruptedAtShutdown,
+// NotWritablePrimary and NotPrimaryNoSecondaryOk.
+const TRANSIENT_CODES: &[i32] = &[6, 7, 89, 91, 189, 11600, 10107, 13435];
+
+/// Whether the database has been initialized. Routes that need it answer 503
+/// until then, instead of not being mounted at all.
+#[derive(Clone)]
+pub struct Readiness(Arc<watch::Sender<State>>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum State {
+    Initializing,
+    Ready,
+    /// Initialization failed with an error that retrying won't fix.
+    Failed(String),
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self(Arc::new(watch::Sender::new(State::Initializing)))
+    }
+
+    pub fn is_ready(&self) -> bool {
+        *self.0.borrow() == State::Ready
+    }
+
+    pub fn set_ready(&self) {
+        self.0.send_replace(State::Ready);
+    }
+
+    pub fn set_failed(&self, message: String) {
+        self.0.send_replace(State::Failed(message));
+    }
+
+    pub async fn wait(&self) {
+        // The sender lives as long as self, so this can't fail.
+        let _ = self
+            .0
+            .subscribe()
+            .wait_for(|state| *state == State::Ready)
+            .await;
+    }
+
+    /// Resolves with the error message once initialization failed for good.
+    pub async fn failed(&self) -> String {
+        let mut receiver = self.0.subscribe();
+
+        loop {
+            if let State::Failed(message) = &*receiver.borrow_and_update() {
+                return message.clone();
+            }
+            // The sender lives as long as self, so this can't fail.
+            let _ = receiver.changed().await;
+        }
+    }
+}
+
+/// Extracts the database handle, rejecting the request with a 503 while the
+/// database is not initialized.
+pub struct Db(pub Database);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Db
+where
+    Database: FromRef<S>,
+    Readiness: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if !Readiness::from_ref(state).is_ready() {
+            return Err(DatabaseError::NotReady.into());
+        }
+
+        Ok(Self(Database::from_ref(state)))
+    }
+}
+
+/// Creates a client for the configured database. The driver connects lazily,
+/// so this only fails when the options are invalid.
+pub async fn client(settings: &settings::Database) -> Result<Database, DatabaseError> {
+    let options = client_options(settings).await?;
+    let client = Client::with_options(options).map_err(DatabaseError::Options)?;
+
+    Ok(client.database(settings.name.as_str()))
+}
+
+/// Creates a client and makes sure the server answers, retrying with an
+/// exponential backoff before giving up.
+pub async fn connect(settings: &settings::Database) -> Result<Database, DatabaseError> {
+    let db = client(settings).await?;
+    ping(&db, settings).await?;
+
+    Ok(db)
+}
+
+pub async fn ping(db: &Database, settings: &settings::Database) -> Result<(), DatabaseError> {
+    let mut backoff = Duration::from_millis(settings.retry_backoff_ms);
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+
+        let err = match db.run_command(doc! { "ping": 1 }, None).await {
+            Ok(_) => return Ok(()),
+            Err(err) => err,
+        };
+
+        if attempt > settings.connect_retries {
+            return Err(DatabaseError::Unavailable {
+                attempts: attempt,
+                source: err,
+            });
+        }
+
+        warn!(
+            "Failed to reach the database (attempt {}), retrying in {:?}: {}",
+            attempt, backoff, err
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+async fn client_options(settings: &settings::Database) -> Result<ClientOptions, DatabaseError> {
+    let mut options = ClientOptions::parse(settings.uri.expose())
         .await
+        .map_err(DatabaseError::Options)?;
+
+    // Settings win over the options in the connection string.
+    if let Some(app_name) = &settings.app_name {
+        options.app_name = Some(app_name.clone());
+    }
+    if let Some(size) = settings.min_pool_size {
+        options.min_pool_size = Some(size);
+    }
+    if let Some(size) = settings.max_pool_size {
+        options.max_pool_size = Some(size);
+    }
+    if let Some(ms) = settings.connect_timeout_ms {
+        options.connect_timeout = Some(Duration::from_millis(ms));
+    }
+    if let Some(ms) = settings.server_selection_timeout_ms {
+        options.server_selection_timeout = Some(Duration::from_millis(ms));
+    }
+    if let Some(mode) = settings.read_preference {
+        options.selection_criteria = Some(SelectionCriteria::ReadPreference(read_preference(mode)));
+    }
+    if let Some(level) = settings.read_concern {
+        options.read_concern = Some(read_concern(level));
+    }
+    if let Some(concern) = &settings.write_concern {
+        options.write_concern = Some(write_concern(concern));
+    }
+    if let Some(path) = &settings.tls_ca_file {
+        let tls = TlsOptions::builder().ca_file_path(path.clone()).build();
+        options.tls = Some(Tls::Enabled(tls));
+    }
+
+    Ok(options)
+}
+
+fn read_preference(mode: ReadPreferenceMode) -> ReadPreference {
+    let options = ReadPreferenceOptions::default();
+
+    match mode {
+        ReadPreferenceMode::Primary => ReadPreference::Primary,
+        ReadPreferenceMode::PrimaryPreferred => ReadPreference::PrimaryPreferred { options },
+        ReadPreferenceMode::Secondary => ReadPreference::Secondary { options },
+        ReadPreferenceMode::SecondaryPreferred => ReadPreference::SecondaryPreferred { options },
+        ReadPreferenceMode::Nearest => ReadPreference::Nearest { options },
+    }
+}
+
+fn read_concern(level: ReadConcernLevel) -> ReadConcern {
+    match level {
+        ReadConcernLevel::Local => ReadConcern::local(),
+        ReadConcernLevel::Majority => ReadConcern::majority(),
+        ReadConcernLevel::Available => ReadConcern::available(),
+        ReadConcernLevel::Linearizable => ReadConcern::linearizable(),
+        ReadConcernLevel::Snapshot => ReadConcern::snapshot(),
+    }
+}
+
+fn write_concern(value: &str) -> WriteConcern {
+    let w = match value.parse::<u32>() {
+        Ok(nodes) => Acknowledgment::Nodes(nodes),
+        Err(_) => Acknowledgment::from(value.to_owned()),
+    };
+
+    WriteConcern::builder().w(w).build()
+}
+
+/// Runs `f` in a transaction, committing when it returns `Ok` and aborting
+/// otherwise. The whole transaction is retried on transient errors, such as a
+/// write conflict or a primary step down, so `f` may run more than once.
+/// Transactions require a replica set or a sharded cluster, on a standalone
+/// server the first operation fails with `Error::Mongo`.
+///
+/// ```ignore
+/// database::with_transaction(&db, |session| {
+///     let db = db.clone();
+///     Box::pin(async move {
+///         Cat::delete_many_with_session(&db, Filter::new().eq(Cat::USER, id), session).await?;
+///         User::delete_one_with_session(&db, Filter::new().eq(User::ID, id), session).await?;
+///         Ok(())
+///     })
+/// })
+/// .await?;
+/// ```
+pub async fn with_transaction<F, T>(db: &Database, mut f: F) -> Result<T, Error>
+where
+    F: for<'a> FnMut(&'a mut ClientSession) -> BoxFuture<'a, Result<T, Error>>,
+{
+    let mut session = db.client().start_session(None).await?;
+    let started = Instant::now();
+
+    'transaction: loop {
+        session.start_transaction(None).await?;
+
+        let value = match f(&mut session).await {
+            Ok(value) => value,
+            Err(err) => {
+                // The server may have aborted it already.
+                let _ = session.abort_transaction().await;
+
+                if has_label(&err, TRANSIENT_TRANSACTION_ERROR)
+                    && started.elapsed() < TRANSACTION_TIMEOUT
+                {
+                    warn!("Retrying transaction after a transient error: {}", err);
+                    continue 'transaction;
+                }
+
+                return Err(err);
+            }
+        };
+
+        loop {
+            let err = match session.commit_transaction().await {
+                Ok(()) => return Ok(value),
+                Err(err) => Error::Mongo(err),
+            };
+
+            if started.elapsed() >= TRANSACTION_TIMEOUT {
+                return Err(err);
+            }
+            if has_label(&err, UNKNOWN_TRANSACTION_COMMIT_RESULT) {
+                continue;
+            }
+            if has_label(&err, TRANSIENT_TRANSACTION_ERROR) {
+                warn!("Retrying transaction after a transient error: {}", err);
+                continue 'transaction;
+            }
+
+            return Err(err);
+        }
+    }
+}
+
+/// Whether the error may go away by itself, such as the server being
+/// unreachable or a replica set without a primary. Anything else, like an
+/// invalid index or a locked migration, fails the same way on every attempt.
+pub fn is_transient(err: &Error) -> bool {
+    let err = match err {
+        Error::Mongo(err) | Error::Wither(WitherError::Mongo(err)) => err,
+        Error::Database(DatabaseError::Unavailable { .. }) => return true,
+        _ => return false,
+    };
+
+    match err.kind.as_ref() {
+        ErrorKind::Io(_)
+        | ErrorKind::ServerSelection { .. }
+        | ErrorKind::ConnectionPoolCleared { .. }
+        | ErrorKind::DnsResolve { .. } => true,
+        ErrorKind::Command(error) => TRANSIENT_CODES.contains(&error.code),
+        _ => false,
+    }
+}
+
+fn has_label(err: &Error, label: &str) -> bool {
+    match err {
+        Error::Mongo(err) | Error::Wither(WitherError::Mongo(err)) => err.contains_label(label),
+        _ => false,
+    }
 }
@@ -1,4 +1,4 @@
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use bcrypt::BcryptError;
@@ -8,6 +8,9 @@ use wither::bson;
 use wither::mongodb::error::Error as MongoError;
 use wither::WitherError;
 
+// Hint sent with 503 responses while the database is initializing.
+const RETRY_AFTER_SECONDS: u64 = 5;
+
 #[derive(thiserror::Error, Debug)]
 #[error("...")]
 pub enum Error {
@@ -32,15 +35,69 @@ pub enum Error {
     #[error("{0}")]
     NotFound(#[from] NotFound),
 
+    #[error("Invalid pagination: {0}")]
+    InvalidPagination(String),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("Invalid patch: {0}")]
+    InvalidPatch(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    #[error("The resource was modified, fetch it again and retry")]
+    PreconditionFailed,
+
+    #[error("Invalid batch: {0}")]
+    InvalidBatch(String),
+
+    #[error("Write failed: {0}")]
+    Write(String),
+
+    #[error("Invalid upload: {0}")]
+    InvalidUpload(String),
+
+    #[error("Uploads can't be larger than {0} bytes")]
+    PayloadTooLarge(u64),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    SerializeJson(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    BlobStore(#[from] BlobStoreError),
+
+    #[error("Failed to serialize a query value: {0}")]
+    SerializeQuery(String),
+
+    #[error("Date out of range: {0}")]
+    DateOutOfRange(String),
+
     #[error("{0}")]
     RunSyncTask(#[from] JoinError),
 
     #[error("{0}")]
     HashPassword(#[from] BcryptError),
+
+    #[error("{0}")]
+    Migration(#[from] MigrationError),
+
+    #[error("Indexes drifted from the models: {0}")]
+    IndexDrift(String),
+
+    #[error("{0}")]
+    Database(#[from] DatabaseError),
 }
 
 impl Error {
-    fn get_codes(&self) -> (StatusCode, u16) {
+    pub fn get_codes(&self) -> (StatusCode, u16) {
         match *self {
             // 4XX Errors
             Error::ParseObjectID(_) => (StatusCode::BAD_REQUEST, 40001),
@@ -53,6 +110,17 @@ impl Error {
                 (StatusCode::UNAUTHORIZED, 40005)
             }
             Error::Authenticate(AuthenticateError::Locked) => (StatusCode::LOCKED, 40006),
+            Error::InvalidPagination(_) => (StatusCode::BAD_REQUEST, 40007),
+            Error::InvalidQuery(_) => (StatusCode::BAD_REQUEST, 40008),
+            Error::InvalidPatch(_) => (StatusCode::BAD_REQUEST, 40009),
+            Error::UnsupportedMediaType(_) => (StatusCode::UNSUPPORTED_MEDIA_TYPE, 40010),
+            Error::PreconditionFailed => (StatusCode::PRECONDITION_FAILED, 40011),
+            Error::InvalidBatch(_) => (StatusCode::BAD_REQUEST, 40012),
+            Error::Write(_) => (StatusCode::UNPROCESSABLE_ENTITY, 40013),
+            Error::InvalidUpload(_) => (StatusCode::BAD_REQUEST, 40014),
+            Error::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, 40015),
+            Error::Forbidden(_) => (StatusCode::FORBIDDEN, 40016),
+            Error::Conflict(_) => (StatusCode::CONFLICT, 40017),
 
             // 5XX Errors
             Error::Authenticate(AuthenticateError::TokenCreation) => {
@@ -63,6 +131,17 @@ impl Error {
             Error::SerializeMongoResponse(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5004),
             Error::RunSyncTask(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5005),
             Error::HashPassword(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5006),
+            Error::Migration(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5007),
+            Error::IndexDrift(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5008),
+            Error::Database(DatabaseError::Unavailable { .. }) => {
+                (StatusCode::SERVICE_UNAVAILABLE, 5009)
+            }
+            Error::Database(DatabaseError::NotReady) => (StatusCode::SERVICE_UNAVAILABLE, 5011),
+            Error::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5010),
+            Error::SerializeJson(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5012),
+            Error::BlobStore(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5013),
+            Error::SerializeQuery(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5014),
+            Error::DateOutOfRange(_) => (StatusCode::INTERNAL_SERVER_ERROR, 5015),
         }
     }
 
@@ -81,6 +160,11 @@ impl IntoResponse for Error {
         let message = self.to_string();
         let body = Json(json!({ "code": code, "message": message }));
 
+        if let Error::Database(DatabaseError::NotReady) = self {
+            let retry_after = [(header::RETRY_AFTER, RETRY_AFTER_SECONDS.to_string())];
+            return (status_code, retry_after, body).into_response();
+        }
+
         (status_code, body).into_response()
     }
 }
@@ -98,6 +182,41 @@ pub enum AuthenticateError {
     Locked,
 }
 
+#[derive(thiserror::Error, Debug)]
+#[error("...")]
+pub enum MigrationError {
+    #[error("Migrations are locked by {0}")]
+    Locked(String),
+    #[error("Migration {0} can not be reverted")]
+    Irreversible(u32),
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("...")]
+pub enum DatabaseError {
+    #[error("Invalid database options: {0}")]
+    Options(MongoError),
+    #[error("Database unavailable after {attempts} attempts: {source}")]
+    Unavailable { attempts: u32, source: MongoError },
+    #[error("Database is not available yet, try again later")]
+    NotReady,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("...")]
+pub enum BlobStoreError {
+    #[error("Invalid blob key {0:?}")]
+    InvalidKey(String),
+    #[error("Blob store IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Blob store database error: {0}")]
+    Mongo(#[from] MongoError),
+    #[error("Blob store request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Blob store answered {status}: {body}")]
+    Status { status: u16, body: String },
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error("Bad Request")]
 pub struct BadRequest {}
@@ -1,17 +1,36 @@
-use std::env;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
 
-use crate::settings::SETTINGS;
+use crate::settings::Logger;
 
-pub fn setup() {
-    if env::var_os("RUST_LOG").is_none() {
-        // compile-time crate name (always available)
-        let app_name = option_env!("CARGO_PKG_NAME").unwrap_or("app");
+pub type ReloadHandle = reload::Handle<EnvFilter, Registry>;
 
-        let level = SETTINGS.logger.level.as_str();
-        let filter = format!("{app_name}={level},tower_http={level}");
+pub fn setup(settings: &Logger) -> ReloadHandle {
+    let (filter, handle) = reload::Layer::new(env_filter(settings));
 
-        env::set_var("RUST_LOG", filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .init();
+
+    handle
+}
+
+pub fn env_filter(settings: &Logger) -> EnvFilter {
+    // RUST_LOG always wins over the configured level.
+    if let Ok(filter) = EnvFilter::try_from_default_env() {
+        return filter;
+    }
+
+    // compile-time crate name (always available)
+    let app_name = option_env!("CARGO_PKG_NAME").unwrap_or("app");
+
+    let level = settings.level.as_str();
+    let mut directives = format!("{app_name}={level},tower_http={level}");
+
+    if let Some(filter) = &settings.filter {
+        directives.push(',');
+        directives.push_str(filter);
     }
 
-    tracing_subscriber::fmt::init();
+    EnvFilter::new(directives)
 }
@@ -1,16 +1,20 @@
-use std::net::SocketAddr;
-use tokio::net::TcpListener;
-use tracing::info;
-
 mod app;
+mod blob_store;
+mod cli;
+mod cors;
 mod database;
 mod errors;
 mod logger;
+mod migrations;
 mod models;
+mod pages;
+mod purge;
+mod reload;
 mod routes;
+mod server;
 mod settings;
+mod state;
 mod utils;
-mod pages;
 
 // There are a couple approaches to take when implementing E2E tests. This
 // approach adds tests on /src/tests, this way tests can reference modules
@@ -20,26 +24,17 @@ mod pages;
 #[cfg(test)]
 mod tests;
 
+use clap::Parser;
 use errors::Error;
-use settings::SETTINGS;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<(), std::io::Error> {
-    // 1. Try to get PORT from environment, fallback to your SETTINGS
-    let port: u16 = std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(SETTINGS.server.port);
-
-    let address = SocketAddr::from(([0, 0, 0, 0], port));
-
-    let app = app::create_app().await;
-
-    let listener = TcpListener::bind(address).await?;
-    
-    // Use println! here to ensure you see this in logs 
-    // even if tracing is not fully initialized yet
-    println!("🚀 Server started on {}", address);
-
-    axum::serve(listener, app).await
-}
\ No newline at end of file
+async fn main() -> ExitCode {
+    match cli::run(cli::Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{Duration, Utc};
+use tokio::task::JoinHandle;
+use tracing::warn;
+use wither::bson::{doc, Document};
+use wither::mongodb::error::{ErrorKind, WriteFailure};
+use wither::mongodb::{Collection, Database};
+
+use crate::errors::{Error, MigrationError};
+
+const COLLECTION: &str = "_migrations_lock";
+const LOCK_ID: &str = "lock";
+const DUPLICATE_KEY: i32 = 11000;
+
+// A lock left behind by an instance that crashed mid-run is taken over once it
+// expires. The holder pushes the expiry back while migrations run, however long
+// they take.
+const EXPIRES_AFTER_SECONDS: i64 = 120;
+const RENEW_EVERY_SECONDS: u64 = 30;
+
+/// Ensures a single instance runs migrations at a time. The lock is a document
+/// with a fixed id, inserting it fails while another instance holds it.
+pub struct Lock {
+    owner: String,
+    heartbeat: JoinHandle<()>,
+}
+
+fn collection(db: &Database) -> Collection<Document> {
+    db.collection::<Document>(COLLECTION)
+}
+
+impl Lock {
+    pub async fn acquire(db: &Database) -> Result<Self, Error> {
+        let owner = format!(
+            "{}:{}",
+            std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_owned()),
+            std::process::id()
+        );
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(EXPIRES_AFTER_SECONDS);
+
+        let lock = doc! {
+            "_id": LOCK_ID,
+            "owner": &owner,
+            "locked_at": now,
+            "expires_at": expires_at,
+        };
+
+        match collection(db).insert_one(lock, None).await {
+            Ok(_) => return Ok(Self::held(db, owner)),
+            Err(err) if !is_duplicate_key(&err) => return Err(err.into()),
+            Err(_) => {}
+        }
+
+        // Take over an expired lock, the filter makes this atomic when several
+        // instances try at once.
+        let taken = collection(db)
+            .find_one_and_update(
+                doc! { "_id": LOCK_ID, "expires_at": { "$lt": now } },
+                doc! { "$set": { "owner": &owner, "locked_at": now, "expires_at": expires_at } },
+                None,
+            )
+            .await?;
+
+        if taken.is_some() {
+            return Ok(Self::held(db, owner));
+        }
+
+        let holder = collection(db)
+            .find_one(doc! { "_id": LOCK_ID }, None)
+            .await?
+            .and_then(|lock| lock.get_str("owner").ok().map(ToOwned::to_owned))
+            .unwrap_or_else(|| "another instance".to_owned());
+
+        Err(MigrationError::Locked(holder).into())
+    }
+
+    fn held(db: &Database, owner: String) -> Self {
+        let heartbeat = tokio::spawn(renew(db.clone(), owner.clone()));
+
+        Self { owner, heartbeat }
+    }
+
+    pub async fn release(self, db: &Database) -> Result<(), Error> {
+        self.heartbeat.abort();
+        collection(db)
+            .delete_one(doc! { "_id": LOCK_ID, "owner": &self.owner }, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+// Stops renewing a lock dropped without being released, it then expires.
+impl Drop for Lock {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+    }
+}
+
+async fn renew(db: Database, owner: String) {
+    let period = std::time::Duration::from_secs(RENEW_EVERY_SECONDS);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+
+    loop {
+        interval.tick().await;
+
+        let expires_at = Utc::now() + Duration::seconds(EXPIRES_AFTER_SECONDS);
+        let result = collection(&db)
+            .update_one(
+                doc! { "_id": LOCK_ID, "owner": &owner },
+                doc! { "$set": { "expires_at": expires_at } },
+                None,
+            )
+            .await;
+
+        match result {
+            Ok(result) if result.matched_count == 0 => {
+                warn!("Lost the migrations lock, another instance may run migrations");
+                return;
+            }
+            Ok(_) => {}
+            // The next tick tries again, the lock lasts a few of them.
+            Err(err) => warn!("Failed to renew the migrations lock: {}", err),
+        }
+    }
+}
+
+fn is_duplicate_key(err: &wither::mongodb::error::Error) -> bool {
+    matches!(
+        *err.kind,
+        ErrorKind::Write(WriteFailure::WriteError(ref error)) if error.code == DUPLICATE_KEY
+    )
+}
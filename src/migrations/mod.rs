@@ -0,0 +1,217 @@
+mod lock;
+mod v0001_user_locked_at;
+mod v0003_cat_details;
+
+use async_trait::async_trait;
+use futures::stream::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+use wither::bson::doc;
+use wither::mongodb::{Collection, Database};
+
+use crate::errors::{Error, MigrationError};
+use crate::utils::date::{self, Date};
+
+use lock::Lock;
+
+const COLLECTION: &str = "_migrations";
+
+// A versioned change to the data. Migrations are applied in version order and
+// recorded in the _migrations collection, so each one runs once per database.
+#[async_trait]
+pub trait Migration: Send + Sync {
+    /// Unique and increasing, new migrations get the next number.
+    fn version(&self) -> u32;
+
+    fn name(&self) -> &'static str;
+
+    /// Describes the operations `up` performs, printed in dry-run mode.
+    fn plan(&self) -> Vec<String>;
+
+    async fn up(&self, db: &Database) -> Result<(), Error>;
+
+    /// Describes the operations `down` performs. Migrations that can't be
+    /// reverted keep the default.
+    fn plan_down(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    async fn down(&self, _db: &Database) -> Result<(), Error> {
+        Err(MigrationError::Irreversible(self.version()).into())
+    }
+}
+
+fn all() -> Vec<Box<dyn Migration>> {
+    vec![
+        Box::new(v0001_user_locked_at::UserLockedAt),
+        Box::new(v0003_cat_details::CatDetails),
+    ]
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    #[serde(rename = "_id")]
+    version: u32,
+    name: String,
+    applied_at: Date,
+}
+
+#[derive(Debug)]
+pub struct Step {
+    pub version: u32,
+    pub name: &'static str,
+    pub plan: Vec<String>,
+    /// When the migration was applied, `None` if it is pending.
+    pub applied_at: Option<Date>,
+}
+
+fn collection(db: &Database) -> Collection<Record> {
+    db.collection::<Record>(COLLECTION)
+}
+
+async fn applied(db: &Database) -> Result<HashMap<u32, Date>, Error> {
+    let records = collection(db)
+        .find(None, None)
+        .await?
+        .try_collect::<Vec<Record>>()
+        .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| (record.version, record.applied_at))
+        .collect())
+}
+
+pub async fn status(db: &Database) -> Result<Vec<Step>, Error> {
+    let applied = applied(db).await?;
+
+    Ok(all()
+        .into_iter()
+        .map(|migration| Step {
+            version: migration.version(),
+            name: migration.name(),
+            plan: migration.plan(),
+            applied_at: applied.get(&migration.version()).copied(),
+        })
+        .collect())
+}
+
+/// Applies every pending migration. With `dry_run` nothing is written, the
+/// returned steps describe what would run.
+pub async fn up(db: &Database, dry_run: bool) -> Result<Vec<Step>, Error> {
+    if dry_run {
+        return Ok(status(db)
+            .await?
+            .into_iter()
+            .filter(|step| step.applied_at.is_none())
+            .collect());
+    }
+
+    let lock = Lock::acquire(db).await?;
+    let result = apply_pending(db).await;
+    lock.release(db).await?;
+
+    result
+}
+
+async fn apply_pending(db: &Database) -> Result<Vec<Step>, Error> {
+    // Read after taking the lock, another instance may have just finished.
+    let applied = applied(db).await?;
+    let mut steps = Vec::new();
+
+    for migration in all() {
+        if applied.contains_key(&migration.version()) {
+            continue;
+        }
+
+        info!(
+            "Applying migration {} {}",
+            migration.version(),
+            migration.name()
+        );
+        migration.up(db).await?;
+
+        let applied_at = date::now();
+        let record = Record {
+            version: migration.version(),
+            name: migration.name().to_owned(),
+            applied_at,
+        };
+        collection(db).insert_one(record, None).await?;
+
+        steps.push(Step {
+            version: migration.version(),
+            name: migration.name(),
+            plan: migration.plan(),
+            applied_at: Some(applied_at),
+        });
+    }
+
+    Ok(steps)
+}
+
+/// Reverts applied migrations newer than `target`, latest first.
+pub async fn down(db: &Database, target: u32, dry_run: bool) -> Result<Vec<Step>, Error> {
+    if dry_run {
+        let applied = applied(db).await?;
+        return reverts(&applied, target).map(|(_, steps)| steps);
+    }
+
+    let lock = Lock::acquire(db).await?;
+    let result = revert_applied(db, target).await;
+    lock.release(db).await?;
+
+    result
+}
+
+// The applied migrations newer than `target`, latest first, with their steps.
+// Fails before anything runs if one of them can't be reverted.
+fn reverts(
+    applied: &HashMap<u32, Date>,
+    target: u32,
+) -> Result<(Vec<Box<dyn Migration>>, Vec<Step>), Error> {
+    let mut migrations = all()
+        .into_iter()
+        .filter(|migration| migration.version() > target)
+        .filter(|migration| applied.contains_key(&migration.version()))
+        .collect::<Vec<_>>();
+    migrations.reverse();
+
+    let mut steps = Vec::new();
+    for migration in &migrations {
+        let plan = migration
+            .plan_down()
+            .ok_or(MigrationError::Irreversible(migration.version()))?;
+
+        steps.push(Step {
+            version: migration.version(),
+            name: migration.name(),
+            plan,
+            applied_at: applied.get(&migration.version()).copied(),
+        });
+    }
+
+    Ok((migrations, steps))
+}
+
+async fn revert_applied(db: &Database, target: u32) -> Result<Vec<Step>, Error> {
+    // Read after taking the lock, another instance may have just finished.
+    let applied = applied(db).await?;
+    let (migrations, steps) = reverts(&applied, target)?;
+
+    for migration in &migrations {
+        info!(
+            "Reverting migration {} {}",
+            migration.version(),
+            migration.name()
+        );
+        migration.down(db).await?;
+
+        collection(db)
+            .delete_one(doc! { "_id": migration.version() }, None)
+            .await?;
+    }
+
+    Ok(steps)
+}
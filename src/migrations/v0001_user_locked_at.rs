@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use wither::bson::doc;
+use wither::mongodb::Database;
+use wither::Model;
+
+use super::Migration;
+use crate::errors::Error;
+use crate::models::user::User;
+
+/// Users created before `locked_at` was introduced don't have the field.
+pub struct UserLockedAt;
+
+#[async_trait]
+impl Migration for UserLockedAt {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "user_locked_at"
+    }
+
+    fn plan(&self) -> Vec<String> {
+        vec![format!(
+            "{}: set locked_at to null where it is missing",
+            User::COLLECTION_NAME
+        )]
+    }
+
+    async fn up(&self, db: &Database) -> Result<(), Error> {
+        User::collection(db)
+            .update_many(
+                doc! { "locked_at": { "$exists": false } },
+                doc! { "$set": { "locked_at": null } },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn plan_down(&self) -> Option<Vec<String>> {
+        Some(vec![format!(
+            "{}: unset locked_at where it is null",
+            User::COLLECTION_NAME
+        )])
+    }
+
+    async fn down(&self, db: &Database) -> Result<(), Error> {
+        User::collection(db)
+            .update_many(
+                doc! { "locked_at": null },
+                doc! { "$unset": { "locked_at": "" } },
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use wither::bson::doc;
+use wither::mongodb::Database;
+use wither::Model;
+
+use super::Migration;
+use crate::errors::Error;
+use crate::models::cat::Cat;
+
+/// Backfills the breed, birthdate, sex, weights, tags and notes added to cats,
+/// so documents created before have the same shape as new ones.
+pub struct CatDetails;
+
+const OPTIONAL_FIELDS: [&str; 4] = ["breed", "birthdate", "sex", "notes"];
+const LIST_FIELDS: [&str; 2] = ["weights", "tags"];
+
+#[async_trait]
+impl Migration for CatDetails {
+    fn version(&self) -> u32 {
+        3
+    }
+
+    fn name(&self) -> &'static str {
+        "cat_details"
+    }
+
+    fn plan(&self) -> Vec<String> {
+        let optional = OPTIONAL_FIELDS.iter().map(|field| {
+            format!(
+                "{}: set {} to null where it is missing",
+                Cat::COLLECTION_NAME,
+                field
+            )
+        });
+        let lists = LIST_FIELDS.iter().map(|field| {
+            format!(
+                "{}: set {} to [] where it is missing",
+                Cat::COLLECTION_NAME,
+                field
+            )
+        });
+
+        optional.chain(lists).collect()
+    }
+
+    async fn up(&self, db: &Database) -> Result<(), Error> {
+        for field in OPTIONAL_FIELDS {
+            Cat::collection(db)
+                .update_many(
+                    doc! { field: { "$exists": false } },
+                    doc! { "$set": { field: null } },
+                    None,
+                )
+                .await?;
+        }
+
+        for field in LIST_FIELDS {
+            Cat::collection(db)
+                .update_many(
+                    doc! { field: { "$exists": false } },
+                    doc! { "$set": { field: [] } },
+                    None,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
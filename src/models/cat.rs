@@ -1,34 +1,141 @@
 use bson::serde_helpers::bson_datetime_as_rfc3339_string;
 use bson::serde_helpers::serialize_object_id_as_hex_string;
+use rustapi_macros::Fields;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 use wither::bson::{doc, oid::ObjectId};
 use wither::Model as WitherModel;
 
 use crate::utils::date;
 use crate::utils::date::Date;
+use crate::utils::list_query::Listable;
 use crate::utils::models::ModelExt;
+use crate::utils::to_object_id;
 
-impl ModelExt for Cat {}
+impl ModelExt for Cat {
+    const SOFT_DELETE_FIELD: Option<&'static str> = Some(Cat::DELETED_AT.name());
+}
+
+const MAX_TAG_LENGTH: usize = 30;
 
-#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
-#[model(index(keys = r#"doc!{ "user": 1, "created_at": 1 }"#))]
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate, Fields)]
+#[model(
+    // The list's default order and its cursor, see `utils::pagination`.
+    index(keys = r#"doc!{ "user": 1, "created_at": 1, "_id": 1 }"#),
+    index(keys = r#"doc!{ "user": 1, "tags": 1 }"#),
+    index(keys = r#"doc!{ "household": 1, "created_at": 1, "_id": 1 }"#),
+    index(keys = r#"doc!{ "purge_at": 1 }"#)
+)]
 pub struct Cat {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    /// The user who created the cat, it always has access to it.
     pub user: ObjectId,
+    /// Shares the cat with the household's members, see `utils::cat_access`.
+    #[serde(default)]
+    pub household: Option<ObjectId>,
+    #[validate(length(min = 1, max = 100))]
     pub name: String,
+    // The fields below were added after the first release, documents created
+    // before are backfilled by the v0003 migration.
+    #[serde(default)]
+    #[validate(length(min = 1, max = 100))]
+    pub breed: Option<String>,
+    #[serde(default)]
+    #[validate(custom(function = "validate_birthdate"))]
+    pub birthdate: Option<Date>,
+    #[serde(default)]
+    pub sex: Option<Sex>,
+    /// Weight measurements, oldest first.
+    #[serde(default)]
+    #[validate(length(max = 500), nested)]
+    pub weights: Vec<Weight>,
+    #[serde(default)]
+    #[validate(length(max = 20), custom(function = "validate_tags"))]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    #[validate(length(max = 2000))]
+    pub notes: Option<String>,
+    /// Set when the cat is moved to the trash.
+    #[serde(default)]
+    pub deleted_at: Option<Date>,
+    /// When a trashed cat is deleted for good, see `purge::purge_trash`.
+    #[serde(default)]
+    pub purge_at: Option<Date>,
     pub updated_at: Date,
     pub created_at: Date,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct Weight {
+    #[validate(range(min = 0.05, max = 30.0))]
+    pub kilograms: f64,
+    pub measured_at: Date,
+}
+
+fn validate_birthdate(birthdate: &Date) -> Result<(), ValidationError> {
+    if *birthdate > date::now() {
+        return Err(ValidationError::new("birthdate_in_future"));
+    }
+
+    Ok(())
+}
+
+// Tags are matched exactly and listed comma separated in `tags[in]`, so they
+// can't contain commas.
+fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    let valid = tags
+        .iter()
+        .all(|tag| !tag.is_empty() && tag.chars().count() <= MAX_TAG_LENGTH && !tag.contains(','));
+
+    if !valid {
+        return Err(ValidationError::new("invalid_tag"));
+    }
+
+    Ok(())
+}
+
+impl Listable for Cat {
+    const TEXT_FIELDS: &'static [&'static str] = &[Cat::NAME.name(), Cat::BREED.name()];
+    const EQUALITY_FIELDS: &'static [&'static str] = &[Cat::BREED.name(), Cat::TAGS.name()];
+    const DATE_FIELDS: &'static [&'static str] = &[
+        Cat::BIRTHDATE.name(),
+        Cat::CREATED_AT.name(),
+        Cat::UPDATED_AT.name(),
+    ];
+    const SORT_FIELDS: &'static [&'static str] = &[
+        Cat::NAME.name(),
+        Cat::BREED.name(),
+        Cat::BIRTHDATE.name(),
+        Cat::CREATED_AT.name(),
+        Cat::UPDATED_AT.name(),
+    ];
+    const DEFAULT_SORT: &'static str = Cat::CREATED_AT.name();
+}
+
 impl Cat {
     pub fn new(user: ObjectId, name: String) -> Self {
         let now = date::now();
         Self {
             id: None,
             user,
+            household: None,
             name,
+            breed: None,
+            birthdate: None,
+            sex: None,
+            weights: Vec::new(),
+            tags: Vec::new(),
+            notes: None,
+            deleted_at: None,
+            purge_at: None,
             updated_at: now,
             created_at: now,
         }
@@ -41,7 +148,20 @@ pub struct PublicCat {
     pub id: ObjectId,
     #[serde(serialize_with = "serialize_object_id_as_hex_string")]
     pub user: ObjectId,
+    #[serde(default, with = "to_object_id::option_hex")]
+    pub household: Option<ObjectId>,
     pub name: String,
+    pub breed: Option<String>,
+    #[serde(with = "date::option_rfc3339")]
+    pub birthdate: Option<Date>,
+    pub sex: Option<Sex>,
+    pub weights: Vec<PublicWeight>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    #[serde(default, with = "date::option_rfc3339")]
+    pub deleted_at: Option<Date>,
+    #[serde(default, with = "date::option_rfc3339")]
+    pub purge_at: Option<Date>,
     #[serde(with = "bson_datetime_as_rfc3339_string")]
     pub updated_at: Date,
     #[serde(with = "bson_datetime_as_rfc3339_string")]
@@ -53,9 +173,43 @@ impl From<Cat> for PublicCat {
         Self {
             id: cat.id.unwrap(),
             user: cat.user,
-            name: cat.name.clone(),
+            household: cat.household,
+            name: cat.name,
+            breed: cat.breed,
+            birthdate: cat.birthdate,
+            sex: cat.sex,
+            weights: cat.weights.into_iter().map(Into::into).collect(),
+            tags: cat.tags,
+            notes: cat.notes,
+            deleted_at: cat.deleted_at,
+            purge_at: cat.purge_at,
             updated_at: cat.updated_at,
             created_at: cat.created_at,
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicWeight {
+    pub kilograms: f64,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub measured_at: Date,
+}
+
+impl From<Weight> for PublicWeight {
+    fn from(weight: Weight) -> Self {
+        Self {
+            kilograms: weight.kilograms,
+            measured_at: weight.measured_at,
+        }
+    }
+}
+
+impl From<PublicWeight> for Weight {
+    fn from(weight: PublicWeight) -> Self {
+        Self {
+            kilograms: weight.kilograms,
+            measured_at: weight.measured_at,
+        }
+    }
+}
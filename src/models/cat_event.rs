@@ -0,0 +1,170 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use rustapi_macros::Fields;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+use wither::bson::{doc, oid::ObjectId, Bson, Document};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::list_query::Listable;
+use crate::utils::models::ModelExt;
+use crate::utils::to_object_id;
+
+impl ModelExt for CatEvent {}
+
+const MAX_PAYLOAD_FIELDS: usize = 50;
+
+/// Something that happened to a cat at `occurred_at`, such as a vet visit or
+/// a feeding. The payload is free form except for the fields each type
+/// requires, see `validate_event`.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate, Fields)]
+#[model(
+    // The list's default order and its cursor, see `routes::cat_event`.
+    index(keys = r#"doc!{ "cat": 1, "occurred_at": -1, "_id": -1 }"#)
+)]
+#[validate(schema(function = "validate_event"))]
+pub struct CatEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub cat: ObjectId,
+    /// The user who recorded the event.
+    pub user: ObjectId,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    pub occurred_at: Date,
+    #[serde(default)]
+    pub payload: Document,
+    /// A photo of the same cat.
+    #[serde(default)]
+    pub attachment: Option<ObjectId>,
+    pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// Needs a `kilograms` number in the payload.
+    Weight,
+    VetVisit,
+    Feeding,
+    Medication,
+    Note,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Weight => "weight",
+            EventKind::VetVisit => "vet_visit",
+            EventKind::Feeding => "feeding",
+            EventKind::Medication => "medication",
+            EventKind::Note => "note",
+        }
+    }
+}
+
+fn validate_event(event: &CatEvent) -> Result<(), ValidationError> {
+    if event.payload.len() > MAX_PAYLOAD_FIELDS {
+        return Err(ValidationError::new("too_many_payload_fields"));
+    }
+
+    // Keys starting with `$` or containing `.` would be read as operators
+    // and paths by queries on the payload.
+    if !valid_keys(&event.payload) {
+        return Err(ValidationError::new("invalid_payload_key"));
+    }
+
+    if event.kind == EventKind::Weight {
+        let kilograms = match event.payload.get("kilograms") {
+            Some(Bson::Double(kilograms)) => *kilograms,
+            Some(Bson::Int32(kilograms)) => f64::from(*kilograms),
+            Some(Bson::Int64(kilograms)) => *kilograms as f64,
+            _ => return Err(ValidationError::new("missing_kilograms")),
+        };
+
+        // Same range as `cat::Weight`.
+        if !(0.05..=30.0).contains(&kilograms) {
+            return Err(ValidationError::new("invalid_kilograms"));
+        }
+    }
+
+    Ok(())
+}
+
+fn valid_keys(document: &Document) -> bool {
+    document
+        .iter()
+        .all(|(key, value)| !key.starts_with('$') && !key.contains('.') && valid_value(value))
+}
+
+fn valid_value(value: &Bson) -> bool {
+    match value {
+        Bson::Document(document) => valid_keys(document),
+        Bson::Array(values) => values.iter().all(valid_value),
+        _ => true,
+    }
+}
+
+impl CatEvent {
+    pub fn new(cat: ObjectId, user: ObjectId, kind: EventKind, occurred_at: Date) -> Self {
+        Self {
+            id: None,
+            cat,
+            user,
+            kind,
+            occurred_at,
+            payload: Document::new(),
+            attachment: None,
+            created_at: date::now(),
+        }
+    }
+}
+
+impl Listable for CatEvent {
+    const TEXT_FIELDS: &'static [&'static str] = &[];
+    const EQUALITY_FIELDS: &'static [&'static str] = &[CatEvent::KIND.name()];
+    const DATE_FIELDS: &'static [&'static str] =
+        &[CatEvent::OCCURRED_AT.name(), CatEvent::CREATED_AT.name()];
+    const SORT_FIELDS: &'static [&'static str] = &[
+        CatEvent::KIND.name(),
+        CatEvent::OCCURRED_AT.name(),
+        CatEvent::CREATED_AT.name(),
+    ];
+    const DEFAULT_SORT: &'static str = CatEvent::OCCURRED_AT.name();
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicCatEvent {
+    #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+    pub id: ObjectId,
+    #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+    pub cat: ObjectId,
+    #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+    pub user: ObjectId,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub occurred_at: Date,
+    pub payload: Document,
+    #[serde(default, with = "to_object_id::option_hex")]
+    pub attachment: Option<ObjectId>,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub created_at: Date,
+}
+
+impl From<CatEvent> for PublicCatEvent {
+    fn from(event: CatEvent) -> Self {
+        Self {
+            id: event.id.unwrap(),
+            cat: event.cat,
+            user: event.user,
+            kind: event.kind,
+            occurred_at: event.occurred_at,
+            payload: event.payload,
+            attachment: event.attachment,
+            created_at: event.created_at,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use rustapi_macros::Fields;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Household {}
+
+/// A group of users sharing their cats. Cats with a `household` are available
+/// to its members, according to their role.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate, Fields)]
+#[model(index(keys = r#"doc!{ "members.user": 1 }"#))]
+pub struct Household {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1, max = 100))]
+    pub members: Vec<Member>,
+    pub updated_at: Date,
+    pub created_at: Date,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    pub user: ObjectId,
+    pub role: Role,
+    pub joined_at: Date,
+}
+
+/// Membership roles, from the least to the most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Reads the household's cats.
+    Viewer,
+    /// Reads and writes the household's cats.
+    Member,
+    /// Also manages the household's members and invites.
+    Owner,
+}
+
+impl Role {
+    pub fn can_write(self) -> bool {
+        self >= Role::Member
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Member => "member",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+impl Household {
+    pub fn new(name: String, owner: ObjectId) -> Self {
+        let now = date::now();
+        Self {
+            id: None,
+            name,
+            members: vec![Member::new(owner, Role::Owner)],
+            updated_at: now,
+            created_at: now,
+        }
+    }
+
+    pub fn role_of(&self, user: &ObjectId) -> Option<Role> {
+        self.members
+            .iter()
+            .find(|member| member.user == *user)
+            .map(|member| member.role)
+    }
+}
+
+impl Member {
+    pub fn new(user: ObjectId, role: Role) -> Self {
+        Self {
+            user,
+            role,
+            joined_at: date::now(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicHousehold {
+    #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+    pub id: ObjectId,
+    pub name: String,
+    pub members: Vec<PublicMember>,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub updated_at: Date,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub created_at: Date,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicMember {
+    #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+    pub user: ObjectId,
+    pub role: Role,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub joined_at: Date,
+}
+
+impl From<Household> for PublicHousehold {
+    fn from(household: Household) -> Self {
+        Self {
+            id: household.id.unwrap(),
+            name: household.name,
+            members: household.members.into_iter().map(Into::into).collect(),
+            updated_at: household.updated_at,
+            created_at: household.created_at,
+        }
+    }
+}
+
+impl From<Member> for PublicMember {
+    fn from(member: Member) -> Self {
+        Self {
+            user: member.user,
+            role: member.role,
+            joined_at: member.joined_at,
+        }
+    }
+}
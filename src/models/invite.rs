@@ -0,0 +1,99 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use chrono::Duration;
+use rustapi_macros::Fields;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::errors::Error;
+use crate::models::household::Role;
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Invite {}
+
+/// An invitation to join a household, accepted by the user with the invited
+/// email. A new invite for the same email replaces the previous one.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate, Fields)]
+#[model(
+    index(keys = r#"doc!{ "email": 1 }"#),
+    index(
+        keys = r#"doc!{ "household": 1, "email": 1 }"#,
+        options = r#"doc!{ "unique": true }"#
+    ),
+    // Deletes invites once they expire.
+    index(
+        keys = r#"doc!{ "expires_at": 1 }"#,
+        options = r#"doc!{ "expireAfterSeconds": 0 }"#
+    )
+)]
+pub struct Invite {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub household: ObjectId,
+    #[validate(email)]
+    pub email: String,
+    pub role: Role,
+    pub invited_by: ObjectId,
+    /// The TTL monitor runs once a minute, queries also check this.
+    pub expires_at: Date,
+    pub created_at: Date,
+}
+
+impl Invite {
+    pub fn new(
+        household: ObjectId,
+        email: String,
+        role: Role,
+        invited_by: ObjectId,
+        ttl_days: u32,
+    ) -> Result<Self, Error> {
+        let now = date::now();
+        let expires_at = Duration::try_days(i64::from(ttl_days))
+            .and_then(|ttl| now.to_chrono().checked_add_signed(ttl))
+            .ok_or_else(|| Error::DateOutOfRange(format!("invite TTL of {} days", ttl_days)))?;
+
+        Ok(Self {
+            id: None,
+            household,
+            email,
+            role,
+            invited_by,
+            expires_at: Date::from_chrono(expires_at),
+            created_at: now,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicInvite {
+    #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+    pub id: ObjectId,
+    #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+    pub household: ObjectId,
+    pub email: String,
+    pub role: Role,
+    #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+    pub invited_by: ObjectId,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub expires_at: Date,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub created_at: Date,
+}
+
+impl From<Invite> for PublicInvite {
+    fn from(invite: Invite) -> Self {
+        Self {
+            id: invite.id.unwrap(),
+            household: invite.household,
+            email: invite.email,
+            role: invite.role,
+            invited_by: invite.invited_by,
+            expires_at: invite.expires_at,
+            created_at: invite.created_at,
+        }
+    }
+}
@@ -1,12 +1,67 @@
 pub mod cat;
+pub mod cat_event;
+pub mod household;
+pub mod invite;
+pub mod photo;
 pub mod user;
 
-use crate::utils::models::ModelExt;
+use tracing::{info, warn};
+use wither::mongodb::Database;
+
+use crate::settings::{IndexMode, Indexes};
+use crate::utils::models::{IndexDrift, ModelExt};
 use crate::Error;
 
-pub async fn sync_indexes() -> Result<(), Error> {
-    user::User::sync_indexes().await?;
-    cat::Cat::sync_indexes().await?;
+pub async fn sync_indexes(db: &Database, settings: &Indexes) -> Result<(), Error> {
+    let background = settings.background;
+
+    match settings.mode {
+        IndexMode::Off => info!("Index sync is off, leaving indexes untouched"),
+        IndexMode::CreateOnly => {
+            user::User::create_indexes(db, background).await?;
+            cat::Cat::create_indexes(db, background).await?;
+            cat_event::CatEvent::create_indexes(db, background).await?;
+            household::Household::create_indexes(db, background).await?;
+            invite::Invite::create_indexes(db, background).await?;
+            photo::Photo::create_indexes(db, background).await?;
+        }
+        IndexMode::Sync => {
+            user::User::sync_indexes(db, background).await?;
+            cat::Cat::sync_indexes(db, background).await?;
+            cat_event::CatEvent::sync_indexes(db, background).await?;
+            household::Household::sync_indexes(db, background).await?;
+            invite::Invite::sync_indexes(db, background).await?;
+            photo::Photo::sync_indexes(db, background).await?;
+        }
+        IndexMode::Verify => {
+            let drifts = index_drift(db).await?;
+            for drift in &drifts {
+                warn!("Indexes drifted from the models, {}", drift);
+            }
+
+            if settings.fail_on_drift && !drifts.is_empty() {
+                let drifts = drifts.iter().map(ToString::to_string).collect::<Vec<_>>();
+                return Err(Error::IndexDrift(drifts.join("; ")));
+            }
+        }
+    }
 
     Ok(())
 }
+
+/// Collections whose indexes differ from the ones declared on the models.
+pub async fn index_drift(db: &Database) -> Result<Vec<IndexDrift>, Error> {
+    let drifts = vec![
+        user::User::index_drift(db).await?,
+        cat::Cat::index_drift(db).await?,
+        cat_event::CatEvent::index_drift(db).await?,
+        household::Household::index_drift(db).await?,
+        invite::Invite::index_drift(db).await?,
+        photo::Photo::index_drift(db).await?,
+    ];
+
+    Ok(drifts
+        .into_iter()
+        .filter(|drift| !drift.is_empty())
+        .collect())
+}
@@ -0,0 +1,88 @@
+use bson::serde_helpers::bson_datetime_as_rfc3339_string;
+use bson::serde_helpers::serialize_object_id_as_hex_string;
+use rustapi_macros::Fields;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::{doc, oid::ObjectId};
+use wither::Model as WitherModel;
+
+use crate::utils::date;
+use crate::utils::date::Date;
+use crate::utils::models::ModelExt;
+
+impl ModelExt for Photo {}
+
+/// A cat's photo. The image and its thumbnail live in the blob store, under
+/// keys derived from the photo id. Photos are never modified, only deleted.
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate, Fields)]
+#[model(index(keys = r#"doc!{ "cat": 1, "created_at": 1 }"#))]
+pub struct Photo {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user: ObjectId,
+    pub cat: ObjectId,
+    /// Sniffed from the upload, one of the types accepted by `utils::images`.
+    pub content_type: String,
+    pub size: i64,
+    pub width: u32,
+    pub height: u32,
+    pub created_at: Date,
+}
+
+impl Photo {
+    pub fn new(
+        user: ObjectId,
+        cat: ObjectId,
+        content_type: String,
+        size: i64,
+        (width, height): (u32, u32),
+    ) -> Self {
+        Self {
+            // Set upfront, the blobs are stored before the document.
+            id: Some(ObjectId::new()),
+            user,
+            cat,
+            content_type,
+            size,
+            width,
+            height,
+            created_at: date::now(),
+        }
+    }
+
+    pub fn blob_key(&self) -> String {
+        format!("photos/{}/original", self.id.unwrap().to_hex())
+    }
+
+    pub fn thumbnail_key(&self) -> String {
+        format!("photos/{}/thumbnail", self.id.unwrap().to_hex())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicPhoto {
+    #[serde(alias = "_id", serialize_with = "serialize_object_id_as_hex_string")]
+    pub id: ObjectId,
+    #[serde(serialize_with = "serialize_object_id_as_hex_string")]
+    pub cat: ObjectId,
+    pub content_type: String,
+    pub size: i64,
+    pub width: u32,
+    pub height: u32,
+    #[serde(with = "bson_datetime_as_rfc3339_string")]
+    pub created_at: Date,
+}
+
+impl From<Photo> for PublicPhoto {
+    fn from(photo: Photo) -> Self {
+        Self {
+            id: photo.id.unwrap(),
+            cat: photo.cat,
+            content_type: photo.content_type,
+            size: photo.size,
+            width: photo.width,
+            height: photo.height,
+            created_at: photo.created_at,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use bson::serde_helpers::bson_datetime_as_rfc3339_string;
 use bson::serde_helpers::serialize_object_id_as_hex_string;
+use rustapi_macros::Fields;
 use serde::{Deserialize, Serialize};
 use tokio::task;
 use validator::Validate;
@@ -13,7 +14,7 @@ use crate::utils::models::ModelExt;
 
 impl ModelExt for User {}
 
-#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate)]
+#[derive(Debug, Clone, Serialize, Deserialize, WitherModel, Validate, Fields)]
 #[model(index(keys = r#"doc!{ "email": 1 }"#, options = r#"doc!{ "unique": true }"#))]
 pub struct User {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
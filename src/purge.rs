@@ -0,0 +1,78 @@
+use std::time::Duration;
+use tracing::{error, info};
+use wither::bson::{oid::ObjectId, Document};
+use wither::mongodb::Database;
+use wither::Model;
+
+use crate::blob_store::BlobStore;
+use crate::errors::Error;
+use crate::models::cat::Cat;
+use crate::models::cat_event::CatEvent;
+use crate::models::photo::Photo;
+use crate::state::AppState;
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::query::Filter;
+
+/// Deletes the cats matching `filter` for good, trashed or not, along with
+/// their events, their photos and the photos' blobs. Returns the number of
+/// deleted cats.
+///
+/// The cats go last, so a run that fails halfway leaves them in place and the
+/// next run picks them up again.
+pub async fn delete_cats(
+    db: &Database,
+    blobs: &dyn BlobStore,
+    filter: Filter<Cat>,
+) -> Result<u64, Error> {
+    let ids = Cat::collection(db)
+        .distinct(Cat::ID.name(), Document::try_from(filter)?, None)
+        .await?
+        .into_iter()
+        .filter_map(|id| id.as_object_id())
+        .collect::<Vec<ObjectId>>();
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let photos = Photo::find(db, Filter::new().is_in(Photo::CAT, ids.clone()), None).await?;
+    for photo in &photos {
+        // The blobs go first, a photo document is what lets a later run find
+        // them again.
+        for key in [photo.blob_key(), photo.thumbnail_key()] {
+            blobs.delete(&key).await?;
+        }
+        Photo::delete_one(db, Filter::new().eq(Photo::ID, photo.id)).await?;
+    }
+
+    CatEvent::delete_many(db, Filter::new().is_in(CatEvent::CAT, ids.clone())).await?;
+    let cats = Cat::delete_many(db, Filter::new().is_in(Cat::ID, ids)).await?;
+
+    Ok(cats.deleted_count)
+}
+
+/// Deletes the cats whose trash retention is over.
+pub async fn purge_trash(db: &Database, blobs: &dyn BlobStore) -> Result<u64, Error> {
+    delete_cats(db, blobs, Filter::new().lte(Cat::PURGE_AT, date::now())).await
+}
+
+/// Purges the trash every `trash.purge_interval_secs` once the database is
+/// initialized.
+pub fn spawn(state: AppState) {
+    let interval = Duration::from_secs(state.settings.trash.purge_interval_secs);
+
+    tokio::spawn(async move {
+        state.db_ready.wait().await;
+
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+
+            match purge_trash(&state.db, state.blobs.as_ref()).await {
+                Ok(0) => {}
+                Ok(count) => info!("Purged {} cats from the trash", count),
+                Err(err) => error!("Failed to purge the trash: {}", err),
+            }
+        }
+    });
+}
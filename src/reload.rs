@@ -0,0 +1,193 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::cors::AllowedOrigins;
+use crate::logger;
+use crate::settings::Settings;
+
+// Settings that can change without restarting the server. Anything else that
+// changes is reported but only takes effect after a restart.
+const RELOADABLE: [&str; 3] = ["logger.level", "logger.filter", "cors.allowed_origins"];
+
+// Editors usually write a file in several steps, changes are applied once the
+// config directory has been quiet for this long.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub key: String,
+    pub from: Json,
+    pub to: Json,
+}
+
+pub struct Reloader {
+    config_dir: PathBuf,
+    run_mode: String,
+    current: Settings,
+    logger: logger::ReloadHandle,
+    allowed_origins: AllowedOrigins,
+}
+
+impl Reloader {
+    pub fn new(
+        config_dir: PathBuf,
+        run_mode: String,
+        current: Settings,
+        logger: logger::ReloadHandle,
+        allowed_origins: AllowedOrigins,
+    ) -> Self {
+        Self {
+            config_dir,
+            run_mode,
+            current,
+            logger,
+            allowed_origins,
+        }
+    }
+
+    /// Reloads the settings on SIGHUP and, when `reload.watch` is enabled,
+    /// whenever a file in the config directory changes.
+    pub fn spawn(mut self) -> std::io::Result<()> {
+        let mut hangup = signal(SignalKind::hangup())?;
+        let (sender, mut changes) = mpsc::unbounded_channel();
+
+        let watcher = if self.current.reload.watch {
+            Some(watch(&self.config_dir, sender).map_err(std::io::Error::other)?)
+        } else {
+            None
+        };
+
+        tokio::spawn(async move {
+            // Dropping the watcher would stop it.
+            let _watcher = watcher;
+
+            loop {
+                tokio::select! {
+                    Some(()) = hangup.recv() => info!("Received SIGHUP, reloading settings"),
+                    Some(()) = changes.recv() => {
+                        tokio::time::sleep(DEBOUNCE).await;
+                        while changes.try_recv().is_ok() {}
+                        info!("Config directory changed, reloading settings");
+                    }
+                    else => break,
+                }
+
+                self.reload();
+            }
+        });
+
+        Ok(())
+    }
+
+    fn reload(&mut self) {
+        let settings = match Settings::from_dir(&self.config_dir, &self.run_mode) {
+            Ok(settings) => settings,
+            Err(err) => {
+                error!("Keeping current settings, reload failed: {}", err);
+                return;
+            }
+        };
+
+        let changes = diff(&self.current, &settings);
+        if changes.is_empty() {
+            info!("Settings reloaded, nothing changed");
+            return;
+        }
+
+        if let Err(err) = self.logger.reload(logger::env_filter(&settings.logger)) {
+            error!("Failed to reload the logger filter: {}", err);
+        }
+        self.allowed_origins.set(&settings.cors.allowed_origins);
+
+        for change in changes.iter().filter(|change| is_reloadable(&change.key)) {
+            info!(setting = %change.key, from = %change.from, to = %change.to, "Setting reloaded");
+        }
+
+        let ignored = ignored(&changes);
+        if !ignored.is_empty() {
+            warn!(settings = %ignored.join(", "), "Ignored changed settings, restart to apply them");
+        }
+
+        // Only keep what was actually applied, so settings that need a restart
+        // are reported again on the next reload.
+        self.current.logger = settings.logger;
+        self.current.cors = settings.cors;
+    }
+}
+
+fn watch(path: &Path, sender: mpsc::UnboundedSender<()>) -> notify::Result<RecommendedWatcher> {
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.kind.is_modify() || event.kind.is_create() => {
+                let _ = sender.send(());
+            }
+            Ok(_) => {}
+            Err(err) => error!("Error watching the config directory: {}", err),
+        })?;
+
+    watcher.watch(path, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
+
+fn is_reloadable(key: &str) -> bool {
+    RELOADABLE.contains(&key)
+}
+
+/// Keys of the changed settings a reload doesn't apply.
+pub fn ignored(changes: &[Change]) -> Vec<&str> {
+    changes
+        .iter()
+        .map(|change| change.key.as_str())
+        .filter(|key| !is_reloadable(key))
+        .collect()
+}
+
+/// Lists every setting that differs between `from` and `to`. Secrets are
+/// compared in their redacted form so they never end up in the logs.
+pub fn diff(from: &Settings, to: &Settings) -> Vec<Change> {
+    let mut before = BTreeMap::new();
+    let mut after = BTreeMap::new();
+    flatten("", from.redacted(), &mut before);
+    flatten("", to.redacted(), &mut after);
+
+    let mut keys = before
+        .keys()
+        .chain(after.keys())
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let from = before.get(&key).cloned().unwrap_or(Json::Null);
+            let to = after.get(&key).cloned().unwrap_or(Json::Null);
+
+            (from != to).then_some(Change { key, from, to })
+        })
+        .collect()
+}
+
+fn flatten(prefix: &str, value: Json, out: &mut BTreeMap<String, Json>) {
+    match value {
+        Json::Object(map) => {
+            for (key, value) in map {
+                let key = match prefix {
+                    "" => key,
+                    prefix => format!("{prefix}.{key}"),
+                };
+                flatten(&key, value, out);
+            }
+        }
+        value => {
+            out.insert(prefix.to_owned(), value);
+        }
+    }
+}
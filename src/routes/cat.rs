@@ -1,35 +1,61 @@
 use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response as AxumResponse};
 use axum::{
-    extract::Path,
-    routing::{delete, get, post, put},
+    extract::{Path, State},
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
-use bson::doc;
+use axum_extra::headers::{ETag, IfMatch, IfNoneMatch};
+use axum_extra::TypedHeader;
+use chrono::Duration;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::debug;
+use validator::Validate;
+use wither::bson::oid::ObjectId;
 use wither::mongodb::options::FindOptions;
+use wither::mongodb::Database;
 
+use crate::database::{self, Db};
 use crate::errors::Error;
-use crate::models::cat::{Cat, PublicCat};
+use crate::models::cat::{Cat, PublicCat, PublicWeight, Sex};
+use crate::settings::{Settings, Trash};
+use crate::state::AppState;
+use crate::utils::cat_access::{CatAccess, Permission};
 use crate::utils::custom_response::CustomResponseResult as Response;
-use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder, ResponsePagination};
-use crate::utils::models::ModelExt;
-use crate::utils::pagination::Pagination;
-use crate::utils::to_object_id::to_object_id;
-use crate::utils::token::TokenUser;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date::{self, Date};
+use crate::utils::etag;
+use crate::utils::list_query::ListQuery;
+use crate::utils::models::{ModelExt, WriteModel, WriteOutcome};
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::patch::Patch;
+use crate::utils::query::{Filter, Sort, Update};
+use crate::utils::to_object_id::{self, to_object_id};
 
-pub fn create_route() -> Router {
+pub fn create_route() -> Router<AppState> {
     Router::new()
         .route("/cats", post(create_cat))
         .route("/cats", get(query_cats))
+        .route("/cats/bulk", post(bulk_cats))
+        .route("/cats/trash", get(query_trash))
+        .route("/cats/:id/restore", post(restore_cat_by_id))
         .route("/cats/:id", get(get_cat_by_id))
         .route("/cats/:id", delete(remove_cat_by_id))
         .route("/cats/:id", put(update_cat_by_id))
+        .route("/cats/:id", patch(patch_cat_by_id))
 }
 
-async fn create_cat(user: TokenUser, Json(payload): Json<CreateCat>) -> Response<PublicCat> {
-    let cat = Cat::new(user.id, payload.name);
-    let cat = Cat::create(cat).await?;
+async fn create_cat(
+    access: CatAccess,
+    Db(db): Db,
+    Json(payload): Json<CreateCat>,
+) -> Result<(TypedHeader<ETag>, CustomResponse<PublicCat>), Error> {
+    let cat = payload.into_cat(access.user.id);
+    access.check_save(None, &cat)?;
+    let cat = Cat::create(&db, cat).await?;
+    let etag = TypedHeader(etag::from_date(cat.updated_at));
     let res = PublicCat::from(cat);
 
     let res = CustomResponseBuilder::new()
@@ -37,60 +63,292 @@ async fn create_cat(user: TokenUser, Json(payload): Json<CreateCat>) -> Response
         .status_code(StatusCode::CREATED)
         .build();
 
-    Ok(res)
+    Ok((etag, res))
 }
 
-async fn query_cats(user: TokenUser, pagination: Pagination) -> Response<Vec<PublicCat>> {
+async fn query_cats(
+    access: CatAccess,
+    Db(db): Db,
+    pagination: Pagination,
+    query: ListQuery<Cat>,
+) -> Response<Vec<PublicCat>> {
+    // Cursors are positions in the default order.
+    if pagination.cursor.is_some() && query.sort.is_some() {
+        return Err(Error::InvalidPagination(
+            "cursor can't be combined with sort".to_owned(),
+        ));
+    }
+
     let options = FindOptions::builder()
-        .sort(doc! { "created_at": -1_i32 })
-        .skip(pagination.offset)
-        .limit(pagination.limit as i64)
+        .sort(query.sort_or_default())
+        .skip(pagination.skip())
+        .limit(pagination.fetch_limit())
         .build();
 
-    let (cats, count) = Cat::find_and_count(doc! { "user": &user.id }, options).await?;
+    let filter = access.filter(Permission::Read).merge(query.filter);
+    let count = Cat::count(&db, filter.clone()).await?;
+
+    let mut cats = Cat::find(&db, filter.merge(pagination.filter::<Cat>()), options).await?;
+    let next_cursor = pagination
+        .next_cursor(&mut cats, |cat| Cursor {
+            date: cat.created_at,
+            id: cat.id.unwrap(),
+        })
+        .filter(|_| query.sort.is_none());
     let cats = cats.into_iter().map(Into::into).collect::<Vec<PublicCat>>();
 
     let res = CustomResponseBuilder::new()
         .body(cats)
-        .pagination(ResponsePagination {
-            count,
-            offset: pagination.offset,
-            limit: pagination.limit,
-        })
+        .pagination(pagination.response(count, next_cursor))
         .build();
 
     debug!("Returning cats");
     Ok(res)
 }
 
-async fn get_cat_by_id(user: TokenUser, Path(id): Path<String>) -> Result<Json<PublicCat>, Error> {
+async fn query_trash(
+    access: CatAccess,
+    Db(db): Db,
+    pagination: Pagination,
+) -> Response<Vec<PublicCat>> {
+    // Cursors are positions in the default order, the trash is sorted by
+    // deleted_at.
+    if pagination.cursor.is_some() {
+        return Err(Error::InvalidPagination(
+            "cursor is not supported in the trash".to_owned(),
+        ));
+    }
+
+    let options = FindOptions::builder()
+        .sort(Sort::new().desc(Cat::DELETED_AT).desc(Cat::ID))
+        .skip(pagination.skip())
+        .limit(i64::from(pagination.limit))
+        .build();
+
+    // Filtering on deleted_at opts out of the soft delete scope.
+    let filter = access
+        .filter(Permission::Read)
+        .ne(Cat::DELETED_AT, None::<Date>);
+    let count = Cat::count(&db, filter.clone()).await?;
+
+    let cats = Cat::find(&db, filter, options).await?;
+    let cats = cats.into_iter().map(Into::into).collect::<Vec<PublicCat>>();
+
+    let res = CustomResponseBuilder::new()
+        .body(cats)
+        .pagination(pagination.response(count, None))
+        .build();
+
+    debug!("Returning trashed cats");
+    Ok(res)
+}
+
+async fn restore_cat_by_id(
+    access: CatAccess,
+    Db(db): Db,
+    Path(id): Path<String>,
+    if_match: Option<TypedHeader<IfMatch>>,
+) -> Result<(TypedHeader<ETag>, Json<PublicCat>), Error> {
     let cat_id = to_object_id(id)?;
-    let cat = Cat::find_one(doc! { "_id": cat_id, "user": &user.id }, None)
-        .await?
-        .map(PublicCat::from);
+    let filter = access
+        .filter(Permission::Read)
+        .eq(Cat::ID, cat_id)
+        .ne(Cat::DELETED_AT, None::<Date>);
+    let Some(cat) = Cat::find_one(&db, filter, None).await? else {
+        debug!("Cat not found in the trash, returning 404 status code");
+        return Err(Error::not_found());
+    };
+    access.check(&cat, Permission::Write)?;
+    etag::check_if_match(if_match.as_deref(), cat.updated_at)?;
 
-    let cat = match cat {
+    let update = Update::new()
+        .set(Cat::DELETED_AT, None::<Date>)
+        .set(Cat::PURGE_AT, None::<Date>)
+        .set(Cat::UPDATED_AT, next_updated_at(&cat));
+
+    let cat = match Cat::find_one_and_update(
+        &db,
+        unchanged(&cat).ne(Cat::DELETED_AT, None::<Date>),
+        update,
+    )
+    .await?
+    {
         Some(cat) => cat,
         None => {
-            debug!("Cat not found, returning 404 status code");
-            return Err(Error::not_found());
+            debug!("Cat changed since it was read, returning 412 status code");
+            return Err(Error::PreconditionFailed);
         }
     };
 
+    debug!("Returning restored cat");
+    Ok((
+        TypedHeader(etag::from_date(cat.updated_at)),
+        Json(cat.into()),
+    ))
+}
+
+async fn get_cat_by_id(
+    access: CatAccess,
+    Db(db): Db,
+    Path(id): Path<String>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> Result<AxumResponse, Error> {
+    let cat = access.find_cat(&db, id, Permission::Read).await?;
+
+    let updated_at = cat.updated_at;
+    let etag = TypedHeader(etag::from_date(updated_at));
+    if etag::is_not_modified(if_none_match.as_deref(), updated_at) {
+        debug!("Cat not modified, returning 304 status code");
+        return Ok((StatusCode::NOT_MODIFIED, etag).into_response());
+    }
+
     debug!("Returning cat");
-    Ok(Json(cat))
+    Ok((etag, Json(PublicCat::from(cat))).into_response())
 }
 
+// Creates, updates and deletes cats in one request. Each operation gets its own
+// result unless the batch is transactional, then the first failing operation
+// fails the request and nothing is written. Transactional batches need a replica
+// set, standalone servers fail them with 500.
+async fn bulk_cats(
+    access: CatAccess,
+    Db(db): Db,
+    State(settings): State<Arc<Settings>>,
+    Json(payload): Json<BulkCats>,
+) -> Result<Json<BulkResponse>, Error> {
+    let max_operations = settings.bulk.max_operations as usize;
+    if payload.operations.is_empty() || payload.operations.len() > max_operations {
+        return Err(Error::InvalidBatch(format!(
+            "expected between 1 and {} operations, got {}",
+            max_operations,
+            payload.operations.len()
+        )));
+    }
+
+    // Updates and deletes apply to the cats as read here, in one query. They
+    // answer 412 if a cat changes before they are written.
+    let mut ids = Vec::new();
+    for operation in &payload.operations {
+        if let Some(Ok(id)) = operation.id().map(ObjectId::parse_str) {
+            if ids.contains(&id) {
+                return Err(Error::InvalidBatch(format!(
+                    "cat {} appears in more than one operation",
+                    id
+                )));
+            }
+            ids.push(id);
+        }
+    }
+
+    let filter = access.filter(Permission::Read).is_in(Cat::ID, ids);
+    let current = Cat::find(&db, filter, None)
+        .await?
+        .into_iter()
+        .filter_map(|cat| cat.id.map(|id| (id, cat)))
+        .collect::<HashMap<ObjectId, Cat>>();
+
+    let writes = payload
+        .operations
+        .into_iter()
+        .map(|operation| operation.into_write(&access, &current, &settings.trash))
+        .collect::<Vec<_>>();
+
+    let results = if payload.transactional {
+        bulk_in_transaction(&db, writes).await?
+    } else {
+        bulk_unordered(&db, writes).await?
+    };
+
+    debug!("Returning bulk results");
+    Ok(Json(BulkResponse { results }))
+}
+
+async fn bulk_unordered(
+    db: &Database,
+    writes: Vec<Result<BulkWrite, Error>>,
+) -> Result<Vec<BulkResult>, Error> {
+    let mut results = Vec::with_capacity(writes.len());
+    let mut operations = Vec::new();
+    let mut pending = Vec::new();
+
+    for write in writes {
+        match write {
+            Ok(write) => {
+                operations.push(write.operation);
+                pending.push((results.len(), write.status, write.id));
+                results.push(BulkResult::default());
+            }
+            Err(error) => results.push(BulkResult::error(error)),
+        }
+    }
+
+    let outcomes = Cat::bulk_write(db, operations).await?;
+    for ((position, status, id), outcome) in pending.into_iter().zip(outcomes) {
+        results[position] = BulkResult::from_outcome(status, id, outcome);
+    }
+
+    Ok(results)
+}
+
+async fn bulk_in_transaction(
+    db: &Database,
+    writes: Vec<Result<BulkWrite, Error>>,
+) -> Result<Vec<BulkResult>, Error> {
+    let writes = writes
+        .into_iter()
+        .enumerate()
+        .map(|(index, write)| write.map_err(|error| operation_failed(index, error)))
+        .collect::<Result<Vec<BulkWrite>, Error>>()?;
+    let operations = writes
+        .iter()
+        .map(|write| write.operation.clone())
+        .collect::<Vec<_>>();
+
+    let outcomes = database::with_transaction(db, |session| {
+        let db = db.clone();
+        let operations = operations.clone();
+        Box::pin(async move {
+            Cat::bulk_write_with_session(&db, operations, session)
+                .await?
+                .into_iter()
+                .enumerate()
+                .map(|(index, outcome)| match outcome {
+                    // Aborts the transaction, the cat changed since it was read.
+                    Ok(WriteOutcome::Unmatched) => Err(Error::PreconditionFailed),
+                    outcome => outcome.map_err(|error| operation_failed(index, error)),
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        })
+    })
+    .await?;
+
+    Ok(writes
+        .into_iter()
+        .zip(outcomes)
+        .map(|(write, outcome)| BulkResult::from_outcome(write.status, write.id, Ok(outcome)))
+        .collect())
+}
+
+fn operation_failed(index: usize, error: Error) -> Error {
+    Error::InvalidBatch(format!("operation {} failed, {}", index, error))
+}
+
+// Moves the cat to the trash, it can be restored until its retention is over.
 async fn remove_cat_by_id(
-    user: TokenUser,
+    access: CatAccess,
+    Db(db): Db,
+    State(settings): State<Arc<Settings>>,
     Path(id): Path<String>,
+    if_match: Option<TypedHeader<IfMatch>>,
 ) -> Result<CustomResponse<()>, Error> {
-    let cat_id = to_object_id(id)?;
-    let delete_result = Cat::delete_one(doc! { "_id": cat_id, "user": &user.id }).await?;
+    let cat = access.find_cat(&db, id, Permission::Write).await?;
+    etag::check_if_match(if_match.as_deref(), cat.updated_at)?;
 
-    if delete_result.deleted_count == 0 {
-        debug!("Cat not found, returning 404 status code");
-        return Err(Error::not_found());
+    let update = trash_update(&cat, &settings.trash);
+    let update_result = Cat::update_one(&db, unchanged(&cat), update, None).await?;
+    if update_result.matched_count == 0 {
+        debug!("Cat changed since it was read, returning 412 status code");
+        return Err(Error::PreconditionFailed);
     }
 
     let res = CustomResponseBuilder::new()
@@ -101,38 +359,318 @@ async fn remove_cat_by_id(
 }
 
 async fn update_cat_by_id(
-    user: TokenUser,
+    access: CatAccess,
+    Db(db): Db,
     Path(id): Path<String>,
+    if_match: Option<TypedHeader<IfMatch>>,
     Json(payload): Json<UpdateCat>,
-) -> Result<Json<PublicCat>, Error> {
-    let cat_id = to_object_id(id)?;
-    let update = bson::to_document(&payload).unwrap();
+) -> Result<(TypedHeader<ETag>, Json<PublicCat>), Error> {
+    let cat = access.find_cat(&db, id, Permission::Write).await?;
+    etag::check_if_match(if_match.as_deref(), cat.updated_at)?;
 
-    let cat = Cat::find_one_and_update(
-        doc! { "_id": &cat_id, "user": &user.id },
-        doc! { "$set": update },
-    )
-    .await?
-    .map(PublicCat::from);
+    let cat = replace_cat(&db, &access, &cat, payload.into_cat(cat.user)).await?;
 
-    let cat = match cat {
-        Some(cat) => cat,
+    debug!("Returning cat");
+    Ok((TypedHeader(etag::from_date(cat.updated_at)), Json(cat)))
+}
+
+async fn patch_cat_by_id(
+    access: CatAccess,
+    Db(db): Db,
+    Path(id): Path<String>,
+    if_match: Option<TypedHeader<IfMatch>>,
+    patch: Patch,
+) -> Result<(TypedHeader<ETag>, Json<PublicCat>), Error> {
+    let cat = access.find_cat(&db, id, Permission::Write).await?;
+    etag::check_if_match(if_match.as_deref(), cat.updated_at)?;
+
+    // The patch applies to the same representation PUT accepts.
+    let mut body = serde_json::to_value(UpdateCat::from(cat.clone()))?;
+    patch.apply(&mut body)?;
+    let payload = serde_json::from_value::<UpdateCat>(body)
+        .map_err(|error| Error::InvalidPatch(error.to_string()))?;
+
+    let cat = replace_cat(&db, &access, &cat, payload.into_cat(cat.user)).await?;
+
+    debug!("Returning patched cat");
+    Ok((TypedHeader(etag::from_date(cat.updated_at)), Json(cat)))
+}
+
+// Matches the cat only while it is as it was read, so a write based on a
+// stale read fails instead of overwriting a concurrent change.
+fn unchanged(cat: &Cat) -> Filter<Cat> {
+    Filter::new()
+        .eq(Cat::ID, cat.id)
+        .eq(Cat::USER, cat.user)
+        .eq(Cat::UPDATED_AT, cat.updated_at)
+}
+
+// The entity tag is derived from updated_at, it must change even when two
+// writes happen within the same millisecond.
+fn next_updated_at(cat: &Cat) -> Date {
+    let now = date::now();
+    if now > cat.updated_at {
+        now
+    } else {
+        Date::from_millis(cat.updated_at.timestamp_millis() + 1)
+    }
+}
+
+// Validates the new state of a cat and sets every editable field to it.
+fn replace_update(current: &Cat, cat: Cat) -> Result<Update<Cat>, Error> {
+    cat.validate().map_err(|_error| Error::bad_request())?;
+
+    let update = Update::new()
+        .set(Cat::HOUSEHOLD, cat.household)
+        .set(Cat::NAME, cat.name)
+        .set(Cat::BREED, cat.breed)
+        .set(Cat::BIRTHDATE, cat.birthdate)
+        .set(Cat::SEX, cat.sex)
+        .set(Cat::WEIGHTS, cat.weights)
+        .set(Cat::TAGS, cat.tags)
+        .set(Cat::NOTES, cat.notes)
+        .set(Cat::UPDATED_AT, next_updated_at(current));
+
+    Ok(update)
+}
+
+// Moves a cat to the trash until its retention is over. `Settings::problems`
+// bounds the retention, a date past what chrono represents would leave the
+// cat in the trash for good instead of panicking.
+fn trash_update(current: &Cat, trash: &Trash) -> Update<Cat> {
+    let now = date::now();
+    let purge_at = Duration::try_days(i64::from(trash.retention_days))
+        .and_then(|retention| now.to_chrono().checked_add_signed(retention))
+        .map(Date::from_chrono);
+
+    Update::new()
+        .set(Cat::DELETED_AT, now)
+        .set(Cat::PURGE_AT, purge_at)
+        .set(Cat::UPDATED_AT, next_updated_at(current))
+}
+
+async fn replace_cat(
+    db: &Database,
+    access: &CatAccess,
+    current: &Cat,
+    cat: Cat,
+) -> Result<PublicCat, Error> {
+    access.check_save(Some(current), &cat)?;
+    let update = replace_update(current, cat)?;
+
+    match Cat::find_one_and_update(db, unchanged(current), update).await? {
+        Some(cat) => Ok(PublicCat::from(cat)),
         None => {
-            debug!("Cat not found, returning 404 status code");
-            return Err(Error::not_found());
+            debug!("Cat changed since it was read, returning 412 status code");
+            Err(Error::PreconditionFailed)
         }
-    };
+    }
+}
 
-    debug!("Returning cat");
-    Ok(Json(cat))
+#[derive(Serialize, Deserialize)]
+pub(super) struct CreateCat {
+    #[serde(default, with = "to_object_id::option_hex")]
+    pub(super) household: Option<ObjectId>,
+    pub(super) name: String,
+    #[serde(default)]
+    pub(super) breed: Option<String>,
+    #[serde(default, with = "date::option_rfc3339")]
+    pub(super) birthdate: Option<Date>,
+    #[serde(default)]
+    pub(super) sex: Option<Sex>,
+    #[serde(default)]
+    pub(super) weights: Vec<PublicWeight>,
+    #[serde(default)]
+    pub(super) tags: Vec<String>,
+    #[serde(default)]
+    pub(super) notes: Option<String>,
+}
+
+impl CreateCat {
+    // Validation happens on the resulting `Cat`.
+    pub(super) fn into_cat(self, user: ObjectId) -> Cat {
+        Cat {
+            household: self.household,
+            breed: self.breed,
+            birthdate: self.birthdate,
+            sex: self.sex,
+            weights: self.weights.into_iter().map(Into::into).collect(),
+            tags: self.tags,
+            notes: self.notes,
+            ..Cat::new(user, self.name)
+        }
+    }
+}
+
+impl From<Cat> for CreateCat {
+    fn from(cat: Cat) -> Self {
+        Self {
+            household: cat.household,
+            name: cat.name,
+            breed: cat.breed,
+            birthdate: cat.birthdate,
+            sex: cat.sex,
+            weights: cat.weights.into_iter().map(Into::into).collect(),
+            tags: cat.tags,
+            notes: cat.notes,
+        }
+    }
 }
 
+// A PUT replaces every editable field, fields left out are cleared.
+type UpdateCat = CreateCat;
+
 #[derive(Deserialize)]
-struct CreateCat {
-    name: String,
+struct BulkCats {
+    /// Apply every operation or none.
+    #[serde(default)]
+    transactional: bool,
+    operations: Vec<BulkOperation>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct UpdateCat {
-    name: String,
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum BulkOperation {
+    Create { cat: CreateCat },
+    Update { id: String, cat: UpdateCat },
+    Delete { id: String },
+}
+
+// An operation checked against the current cats, ready to be written.
+struct BulkWrite {
+    operation: WriteModel<Cat>,
+    status: StatusCode,
+    id: Option<ObjectId>,
+}
+
+impl BulkOperation {
+    fn id(&self) -> Option<&str> {
+        match self {
+            BulkOperation::Create { .. } => None,
+            BulkOperation::Update { id, .. } | BulkOperation::Delete { id } => Some(id.as_str()),
+        }
+    }
+
+    fn into_write(
+        self,
+        access: &CatAccess,
+        current: &HashMap<ObjectId, Cat>,
+        trash: &Trash,
+    ) -> Result<BulkWrite, Error> {
+        let find = |id: String| {
+            let id = to_object_id(id)?;
+            let cat = current.get(&id).ok_or_else(Error::not_found)?;
+            access.check(cat, Permission::Write)?;
+            Ok::<_, Error>(cat)
+        };
+
+        match self {
+            BulkOperation::Create { cat } => {
+                let cat = cat.into_cat(access.user.id);
+                access.check_save(None, &cat)?;
+                cat.validate().map_err(|_error| Error::bad_request())?;
+
+                Ok(BulkWrite {
+                    operation: WriteModel::InsertOne(cat),
+                    status: StatusCode::CREATED,
+                    id: None,
+                })
+            }
+            BulkOperation::Update { id, cat } => {
+                let current = find(id)?;
+                let cat = cat.into_cat(current.user);
+                access.check_save(Some(current), &cat)?;
+                let update = replace_update(current, cat)?;
+
+                Ok(BulkWrite {
+                    operation: WriteModel::UpdateOne {
+                        filter: unchanged(current),
+                        update,
+                    },
+                    status: StatusCode::OK,
+                    id: current.id,
+                })
+            }
+            BulkOperation::Delete { id } => {
+                let current = find(id)?;
+
+                // Deletes move cats to the trash, like `DELETE /cats/:id`.
+                Ok(BulkWrite {
+                    operation: WriteModel::UpdateOne {
+                        filter: unchanged(current),
+                        update: trash_update(current, trash),
+                    },
+                    status: StatusCode::NO_CONTENT,
+                    id: current.id,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BulkResponse {
+    /// One result per operation, in the same order.
+    results: Vec<BulkResult>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct BulkResult {
+    status: u16,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "to_object_id::option_hex::serialize"
+    )]
+    id: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<BulkError>,
+}
+
+#[derive(Debug, Serialize)]
+struct BulkError {
+    code: u16,
+    message: String,
+}
+
+impl BulkResult {
+    fn error(error: Error) -> Self {
+        let (status, code) = error.get_codes();
+
+        Self {
+            status: status.as_u16(),
+            id: None,
+            error: Some(BulkError {
+                code,
+                message: error.to_string(),
+            }),
+        }
+    }
+
+    fn from_outcome(
+        status: StatusCode,
+        id: Option<ObjectId>,
+        outcome: Result<WriteOutcome<Cat>, Error>,
+    ) -> Self {
+        match outcome {
+            // Writes are conditional on the cat as it was read.
+            Ok(WriteOutcome::Unmatched) => Self {
+                id,
+                ..Self::error(Error::PreconditionFailed)
+            },
+            Ok(WriteOutcome::Inserted(cat)) => Self {
+                status: status.as_u16(),
+                id: cat.id,
+                error: None,
+            },
+            Ok(_) => Self {
+                status: status.as_u16(),
+                id,
+                error: None,
+            },
+            Err(error) => Self {
+                id,
+                ..Self::error(error)
+            },
+        }
+    }
 }
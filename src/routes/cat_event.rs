@@ -0,0 +1,299 @@
+use axum::http::StatusCode;
+use axum::{
+    extract::Path,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::debug;
+use wither::bson::{doc, oid::ObjectId, Document};
+use wither::mongodb::options::FindOptions;
+use wither::mongodb::Database;
+
+use crate::database::Db;
+use crate::errors::Error;
+use crate::models::cat::{Cat, PublicWeight, Weight};
+use crate::models::cat_event::{CatEvent, EventKind, PublicCatEvent};
+use crate::models::photo::Photo;
+use crate::state::AppState;
+use crate::utils::cat_access::{CatAccess, Permission};
+use crate::utils::custom_response::CustomResponseResult as Response;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date::{self, Date};
+use crate::utils::list_query::ListQuery;
+use crate::utils::models::ModelExt;
+use crate::utils::pagination::{Cursor, Pagination};
+use crate::utils::query::Filter;
+use crate::utils::to_object_id::{self, to_object_id};
+
+pub fn create_route() -> Router<AppState> {
+    Router::new()
+        .route("/cats/:id/events", post(create_event))
+        .route("/cats/:id/events", get(query_events))
+        .route("/cats/:id/events/summary", get(get_summary))
+        .route("/cats/:id/events/:event_id", get(get_event_by_id))
+        .route("/cats/:id/events/:event_id", delete(remove_event_by_id))
+}
+
+async fn create_event(
+    access: CatAccess,
+    Db(db): Db,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateEvent>,
+) -> Result<CustomResponse<PublicCatEvent>, Error> {
+    let cat = access.find_cat(&db, id, Permission::Write).await?;
+    let cat_id = cat.id.unwrap();
+
+    if let Some(attachment) = payload.attachment {
+        let filter = Filter::new()
+            .eq(Photo::ID, attachment)
+            .eq(Photo::CAT, cat_id);
+        if !Photo::exists(&db, filter).await? {
+            debug!("Attachment is not a photo of the cat, returning 400 status code");
+            return Err(Error::bad_request());
+        }
+    }
+
+    let mut event = CatEvent::new(
+        cat_id,
+        access.user.id,
+        payload.kind,
+        payload.occurred_at.unwrap_or_else(date::now),
+    );
+    event.payload = payload.payload;
+    event.attachment = payload.attachment;
+    let event = CatEvent::create(&db, event).await?;
+
+    let res = CustomResponseBuilder::new()
+        .body(PublicCatEvent::from(event))
+        .status_code(StatusCode::CREATED)
+        .build();
+
+    debug!("Returning created event");
+    Ok(res)
+}
+
+// Date ranges use the list filters, e.g.
+// `occurred_at[gte]=2024-01-01T00:00:00Z&occurred_at[lt]=2024-02-01T00:00:00Z`.
+async fn query_events(
+    access: CatAccess,
+    Db(db): Db,
+    Path(id): Path<String>,
+    pagination: Pagination,
+    query: ListQuery<CatEvent>,
+) -> Response<Vec<PublicCatEvent>> {
+    // Cursors are positions in the default order.
+    if pagination.cursor.is_some() && query.sort.is_some() {
+        return Err(Error::InvalidPagination(
+            "cursor can't be combined with sort".to_owned(),
+        ));
+    }
+
+    let cat = access.find_cat(&db, id, Permission::Read).await?;
+
+    let options = FindOptions::builder()
+        .sort(query.sort_or_default())
+        .skip(pagination.skip())
+        .limit(pagination.fetch_limit())
+        .build();
+
+    let filter = Filter::new()
+        .merge(query.filter)
+        .eq(CatEvent::CAT, cat.id.unwrap());
+    let count = CatEvent::count(&db, filter.clone()).await?;
+
+    let mut events =
+        CatEvent::find(&db, filter.merge(pagination.filter::<CatEvent>()), options).await?;
+    let next_cursor = pagination
+        .next_cursor(&mut events, |event| Cursor {
+            date: event.occurred_at,
+            id: event.id.unwrap(),
+        })
+        .filter(|_| query.sort.is_none());
+    let events = events
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<PublicCatEvent>>();
+
+    let res = CustomResponseBuilder::new()
+        .body(events)
+        .pagination(pagination.response(count, next_cursor))
+        .build();
+
+    debug!("Returning events");
+    Ok(res)
+}
+
+async fn get_event_by_id(
+    access: CatAccess,
+    Db(db): Db,
+    Path((id, event_id)): Path<(String, String)>,
+) -> Result<Json<PublicCatEvent>, Error> {
+    let cat = access.find_cat(&db, id, Permission::Read).await?;
+    let event = find_event(&db, &cat, event_id).await?;
+
+    debug!("Returning event");
+    Ok(Json(PublicCatEvent::from(event)))
+}
+
+async fn remove_event_by_id(
+    access: CatAccess,
+    Db(db): Db,
+    Path((id, event_id)): Path<(String, String)>,
+) -> Result<CustomResponse<()>, Error> {
+    let cat = access.find_cat(&db, id, Permission::Write).await?;
+    let event = find_event(&db, &cat, event_id).await?;
+
+    CatEvent::delete_one(&db, Filter::new().eq(CatEvent::ID, event.id)).await?;
+
+    let res = CustomResponseBuilder::new()
+        .status_code(StatusCode::NO_CONTENT)
+        .build();
+
+    debug!("Event deleted, returning 204 status code");
+    Ok(res)
+}
+
+// Totals per type, the latest weight and the counts per type for every
+// month with events, in UTC. Takes the same filters as the list, so
+// `occurred_at[gte]` narrows it down to a period.
+async fn get_summary(
+    access: CatAccess,
+    Db(db): Db,
+    Path(id): Path<String>,
+    query: ListQuery<CatEvent>,
+) -> Result<Json<Summary>, Error> {
+    let cat = access.find_cat(&db, id, Permission::Read).await?;
+
+    let filter = Filter::<CatEvent>::new()
+        .merge(query.filter)
+        .eq(CatEvent::CAT, cat.id.unwrap());
+    let filter = Document::try_from(filter)?;
+    let pipeline = vec![
+        doc! { "$match": filter },
+        doc! {
+            "$facet": {
+                "totals": [
+                    { "$group": { "_id": "$type", "count": { "$sum": 1 } } },
+                ],
+                "latest_weight": [
+                    { "$match": { "type": EventKind::Weight.as_str() } },
+                    { "$sort": { "occurred_at": -1, "_id": -1 } },
+                    { "$limit": 1 },
+                    {
+                        "$project": {
+                            "_id": 0,
+                            "kilograms": { "$toDouble": "$payload.kilograms" },
+                            "measured_at": "$occurred_at",
+                        }
+                    },
+                ],
+                "months": [
+                    {
+                        "$group": {
+                            "_id": {
+                                "month": {
+                                    "$dateToString": { "format": "%Y-%m", "date": "$occurred_at" }
+                                },
+                                "type": "$type",
+                            },
+                            "count": { "$sum": 1 },
+                        }
+                    },
+                    {
+                        "$group": {
+                            "_id": "$_id.month",
+                            "counts": { "$push": { "k": "$_id.type", "v": "$count" } },
+                        }
+                    },
+                    { "$sort": { "_id": 1 } },
+                    {
+                        "$project": {
+                            "_id": 0,
+                            "month": "$_id",
+                            "counts": { "$arrayToObject": "$counts" },
+                        }
+                    },
+                ],
+            }
+        },
+    ];
+
+    // `$facet` always outputs a single document.
+    let facets = CatEvent::aggregate::<Facets>(&db, pipeline)
+        .await?
+        .pop()
+        .unwrap_or_default();
+
+    let summary = Summary {
+        totals: facets
+            .totals
+            .into_iter()
+            .map(|total| (total.kind, total.count))
+            .collect(),
+        latest_weight: facets.latest_weight.into_iter().next().map(Into::into),
+        months: facets.months,
+    };
+
+    debug!("Returning events summary");
+    Ok(Json(summary))
+}
+
+async fn find_event(db: &Database, cat: &Cat, event_id: String) -> Result<CatEvent, Error> {
+    let event_id = to_object_id(event_id)?;
+    let filter = Filter::new()
+        .eq(CatEvent::ID, event_id)
+        .eq(CatEvent::CAT, cat.id.unwrap());
+
+    match CatEvent::find_one(db, filter, None).await? {
+        Some(event) => Ok(event),
+        None => {
+            debug!("Event not found, returning 404 status code");
+            Err(Error::not_found())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateEvent {
+    #[serde(rename = "type")]
+    kind: EventKind,
+    /// Defaults to now.
+    #[serde(default, with = "date::option_rfc3339")]
+    occurred_at: Option<Date>,
+    #[serde(default)]
+    payload: Document,
+    #[serde(default, with = "to_object_id::option_hex")]
+    attachment: Option<ObjectId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Summary {
+    /// Number of events per type.
+    pub totals: BTreeMap<EventKind, u64>,
+    pub latest_weight: Option<PublicWeight>,
+    pub months: Vec<MonthSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonthSummary {
+    /// `YYYY-MM`, in UTC.
+    pub month: String,
+    /// Number of events per type.
+    pub counts: BTreeMap<EventKind, u64>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Facets {
+    totals: Vec<TypeTotal>,
+    latest_weight: Vec<Weight>,
+    months: Vec<MonthSummary>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TypeTotal {
+    #[serde(rename = "_id")]
+    kind: EventKind,
+    count: u64,
+}
@@ -0,0 +1,388 @@
+use axum::body::{Body, Bytes};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use axum::{
+    routing::{get, post},
+    Json, Router,
+};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error};
+use validator::Validate;
+use wither::mongodb::options::FindOptions;
+
+use super::cat::CreateCat;
+use crate::database::Db;
+use crate::errors::Error;
+use crate::models::cat::{Cat, PublicCat, PublicWeight, Sex};
+use crate::settings::Settings;
+use crate::state::AppState;
+use crate::utils::cat_access::{CatAccess, Permission};
+use crate::utils::date::Date;
+use crate::utils::list_query::ListQuery;
+use crate::utils::models::ModelExt;
+
+// Export and import of a user's cats. Exports are streamed from a cursor, so
+// the response is written as the documents are read. Imports are read in full,
+// within the request body limit, and validated before anything is written.
+
+const CSV: &str = "text/csv";
+const NDJSON: &str = "application/x-ndjson";
+
+const CSV_HEADER: [&str; 10] = [
+    "id",
+    "name",
+    "breed",
+    "birthdate",
+    "sex",
+    "weights",
+    "tags",
+    "notes",
+    "created_at",
+    "updated_at",
+];
+
+pub fn create_route() -> Router<AppState> {
+    Router::new()
+        .route("/cats/export", get(export_cats))
+        .route("/cats/import", post(import_cats))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportParams {
+    format: Option<String>,
+}
+
+async fn export_cats(
+    access: CatAccess,
+    Db(db): Db,
+    Query(params): Query<ExportParams>,
+    query: ListQuery<Cat>,
+) -> Result<Response, Error> {
+    let format = match params.format.as_deref() {
+        None | Some("json") => Format::Json,
+        Some("ndjson") => Format::Ndjson,
+        Some("csv") => Format::Csv,
+        Some(other) => {
+            return Err(Error::InvalidQuery(format!(
+                "format {} is not supported, use json, ndjson or csv",
+                other
+            )))
+        }
+    };
+
+    let options = FindOptions::builder().sort(query.sort_or_default()).build();
+    let filter = access.filter(Permission::Read).merge(query.filter);
+    let cursor = Cat::cursor(&db, filter, options).await?;
+
+    let rows = cursor.enumerate().map(move |(index, cat)| {
+        let cat = PublicCat::from(cat.map_err(Error::Wither)?);
+        match format {
+            Format::Json if index == 0 => json_row("", &cat, ""),
+            Format::Json => json_row(",", &cat, ""),
+            Format::Ndjson => json_row("", &cat, "\n"),
+            Format::Csv => Ok(csv_row(&csv_record(&cat))),
+        }
+    });
+
+    // Errors past the first row can't change the status anymore, the
+    // response is cut short instead.
+    let rows = rows.inspect(|row| {
+        if let Err(err) = row {
+            error!("Error exporting cats: {}", err);
+        }
+    });
+
+    let (content_type, filename, body) = match format {
+        Format::Json => {
+            let open = stream::once(async { Ok(Bytes::from("[")) });
+            let close = stream::once(async { Ok(Bytes::from("]")) });
+            let body = Body::from_stream(open.chain(rows).chain(close));
+            ("application/json", "cats.json", body)
+        }
+        Format::Ndjson => (NDJSON, "cats.ndjson", Body::from_stream(rows)),
+        Format::Csv => {
+            let header = csv_row(&CSV_HEADER.map(ToOwned::to_owned));
+            let header = stream::once(async { Ok::<_, Error>(header) });
+            (CSV, "cats.csv", Body::from_stream(header.chain(rows)))
+        }
+    };
+
+    let headers = [
+        (header::CONTENT_TYPE, HeaderValue::from_static(content_type)),
+        (
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
+                .expect("Filenames are valid header values"),
+        ),
+    ];
+
+    debug!("Streaming cats export");
+    Ok((headers, body).into_response())
+}
+
+fn json_row(prefix: &str, cat: &PublicCat, suffix: &str) -> Result<Bytes, Error> {
+    let json = serde_json::to_vec(cat)?;
+    Ok([prefix.as_bytes(), &json, suffix.as_bytes()]
+        .concat()
+        .into())
+}
+
+fn csv_row(record: &[String]) -> Bytes {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(record)
+        .expect("Writing CSV to memory doesn't fail");
+
+    writer
+        .into_inner()
+        .expect("Writing CSV to memory doesn't fail")
+        .into()
+}
+
+// Lists are comma separated within their field, tags can't contain commas.
+// Weights are written as `kilograms@measured_at`.
+fn csv_record(cat: &PublicCat) -> Vec<String> {
+    let date = |date: &Date| date.try_to_rfc3339_string().unwrap_or_default();
+    let weights = cat
+        .weights
+        .iter()
+        .map(|weight| format!("{}@{}", weight.kilograms, date(&weight.measured_at)))
+        .collect::<Vec<_>>();
+    let sex = cat.sex.map(|sex| match sex {
+        Sex::Female => "female",
+        Sex::Male => "male",
+    });
+
+    vec![
+        cat.id.to_hex(),
+        cat.name.clone(),
+        cat.breed.clone().unwrap_or_default(),
+        cat.birthdate.as_ref().map(date).unwrap_or_default(),
+        sex.unwrap_or_default().to_owned(),
+        weights.join(","),
+        cat.tags.join(","),
+        cat.notes.clone().unwrap_or_default(),
+        date(&cat.created_at),
+        date(&cat.updated_at),
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportParams {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportReport {
+    dry_run: bool,
+    total: usize,
+    valid: usize,
+    imported: usize,
+    /// Rows that were not imported, by line number in the upload.
+    errors: Vec<ImportError>,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportError {
+    line: u64,
+    message: String,
+}
+
+// Accepts the CSV and NDJSON exports, columns and fields other than the cat's
+// editable ones, such as id and created_at, are ignored. Valid rows are
+// imported even when others fail, run with `dry_run=true` to get the report
+// without writing anything.
+async fn import_cats(
+    access: CatAccess,
+    Db(db): Db,
+    State(settings): State<Arc<Settings>>,
+    Query(params): Query<ImportParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ImportReport>, Error> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<mime::Mime>().ok())
+        .map(|mime| mime.essence_str().to_ascii_lowercase());
+
+    let rows = match content_type.as_deref() {
+        Some(CSV) => parse_csv(&body),
+        Some(NDJSON) | Some("application/ndjson") => parse_ndjson(&body),
+        other => {
+            return Err(Error::UnsupportedMediaType(format!(
+                "{} is not supported, use {} or {}",
+                other.unwrap_or("a missing content type"),
+                CSV,
+                NDJSON
+            )))
+        }
+    };
+
+    let total = rows.len();
+    let mut errors = Vec::new();
+    let mut valid = Vec::new();
+    for (line, row) in rows {
+        let cat = row.and_then(|cat| {
+            // Same checks as `POST /cats`, through `Cat::new`.
+            let cat = cat.into_cat(access.user.id);
+            access
+                .check_save(None, &cat)
+                .map_err(|err| err.to_string())?;
+            cat.validate().map_err(|errors| errors.to_string())?;
+            Ok(cat)
+        });
+
+        match cat {
+            Ok(cat) => valid.push((line, cat)),
+            Err(message) => errors.push(ImportError { line, message }),
+        }
+    }
+
+    let valid_count = valid.len();
+    let mut imported = 0;
+    if !params.dry_run {
+        let batch_size = settings.bulk.max_operations.max(1) as usize;
+        for batch in valid.chunks(batch_size) {
+            let (lines, cats): (Vec<u64>, Vec<Cat>) = batch.iter().cloned().unzip();
+            for (line, result) in lines.into_iter().zip(Cat::insert_many(&db, cats).await?) {
+                match result {
+                    Ok(_) => imported += 1,
+                    Err(err) => errors.push(ImportError {
+                        line,
+                        message: err.to_string(),
+                    }),
+                }
+            }
+        }
+    }
+
+    errors.sort_by_key(|error| error.line);
+
+    debug!("Returning import report");
+    Ok(Json(ImportReport {
+        dry_run: params.dry_run,
+        total,
+        valid: valid_count,
+        imported,
+        errors,
+    }))
+}
+
+type Row = (u64, Result<CreateCat, String>);
+
+fn parse_ndjson(body: &[u8]) -> Vec<Row> {
+    String::from_utf8_lossy(body)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let cat = serde_json::from_str::<CreateCat>(line).map_err(|err| err.to_string());
+            (index as u64 + 1, cat)
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct CsvCat {
+    name: String,
+    breed: Option<String>,
+    birthdate: Option<String>,
+    sex: Option<String>,
+    weights: Option<String>,
+    tags: Option<String>,
+    notes: Option<String>,
+}
+
+fn parse_csv(body: &[u8]) -> Vec<Row> {
+    let mut reader = csv::Reader::from_reader(body);
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(err) => return vec![(1, Err(err.to_string()))],
+    };
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record.map_err(|err| (err.position().map(|p| p.line()), err.to_string()));
+            match record {
+                Ok(record) => {
+                    let line = record.position().map(|p| p.line()).unwrap_or_default();
+                    let cat = record
+                        .deserialize::<CsvCat>(Some(&headers))
+                        .map_err(|err| err.to_string())
+                        .and_then(from_csv);
+                    (line, cat)
+                }
+                Err((line, message)) => (line.unwrap_or_default(), Err(message)),
+            }
+        })
+        .collect()
+}
+
+fn from_csv(cat: CsvCat) -> Result<CreateCat, String> {
+    let date = |field: &str, value: &str| {
+        Date::parse_rfc3339_str(value).map_err(|_| format!("{} must be an RFC 3339 date", field))
+    };
+
+    let birthdate = cat
+        .birthdate
+        .map(|birthdate| date("birthdate", &birthdate))
+        .transpose()?;
+
+    let sex = match cat.sex.as_deref() {
+        None => None,
+        Some("female") => Some(Sex::Female),
+        Some("male") => Some(Sex::Male),
+        Some(other) => return Err(format!("sex {} must be female or male", other)),
+    };
+
+    let weights = list(cat.weights)
+        .map(|weight| {
+            let (kilograms, measured_at) = weight
+                .split_once('@')
+                .ok_or_else(|| format!("weight {} must be kilograms@date", weight))?;
+            let kilograms = kilograms
+                .parse::<f64>()
+                .map_err(|_| format!("weight {} must be kilograms@date", weight))?;
+
+            Ok(PublicWeight {
+                kilograms,
+                measured_at: date("weights", measured_at)?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(CreateCat {
+        household: None,
+        name: cat.name,
+        breed: cat.breed,
+        birthdate,
+        sex,
+        weights,
+        tags: list(cat.tags).collect(),
+        notes: cat.notes,
+    })
+}
+
+fn list(value: Option<String>) -> impl Iterator<Item = String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>()
+        .into_iter()
+}
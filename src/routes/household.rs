@@ -0,0 +1,321 @@
+use axum::http::StatusCode;
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::debug;
+use validator::Validate;
+use wither::bson::doc;
+use wither::mongodb::options::{FindOptions, UpdateOptions};
+use wither::mongodb::Database;
+
+use crate::database::Db;
+use crate::errors::Error;
+use crate::models::household::{Household, Member, PublicHousehold, Role};
+use crate::models::invite::{Invite, PublicInvite};
+use crate::models::user::User;
+use crate::settings::Settings;
+use crate::state::AppState;
+use crate::utils::custom_response::CustomResponseResult as Response;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::date;
+use crate::utils::models::ModelExt;
+use crate::utils::query::{Filter, Sort, Update};
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::TokenUser;
+
+pub fn create_route() -> Router<AppState> {
+    Router::new()
+        .route("/households", post(create_household))
+        .route("/households", get(query_households))
+        .route("/households/:id", get(get_household_by_id))
+        .route("/households/:id/invites", post(create_invite))
+        .route(
+            "/households/:id/members/:user_id",
+            delete(remove_member_by_id),
+        )
+        .route("/invites", get(query_invites))
+        .route("/invites/:id/accept", post(accept_invite))
+}
+
+async fn create_household(
+    user: TokenUser,
+    Db(db): Db,
+    Json(payload): Json<CreateHousehold>,
+) -> Result<CustomResponse<PublicHousehold>, Error> {
+    let household = Household::new(payload.name, user.id);
+    let household = Household::create(&db, household).await?;
+
+    let res = CustomResponseBuilder::new()
+        .body(PublicHousehold::from(household))
+        .status_code(StatusCode::CREATED)
+        .build();
+
+    debug!("Returning created household");
+    Ok(res)
+}
+
+async fn query_households(user: TokenUser, Db(db): Db) -> Response<Vec<PublicHousehold>> {
+    let options = FindOptions::builder()
+        .sort(Sort::new().asc(Household::CREATED_AT).asc(Household::ID))
+        .build();
+    let households = Household::find(&db, doc! { "members.user": user.id }, options)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<PublicHousehold>>();
+
+    let res = CustomResponseBuilder::new().body(households).build();
+
+    debug!("Returning households");
+    Ok(res)
+}
+
+async fn get_household_by_id(
+    user: TokenUser,
+    Db(db): Db,
+    Path(id): Path<String>,
+) -> Result<Json<PublicHousehold>, Error> {
+    let (household, _) = find_household(&db, &user, id).await?;
+
+    debug!("Returning household");
+    Ok(Json(PublicHousehold::from(household)))
+}
+
+// Inviting an email again replaces its pending invite, e.g. to change the
+// role or extend the expiration.
+async fn create_invite(
+    user: TokenUser,
+    Db(db): Db,
+    State(settings): State<Arc<Settings>>,
+    Path(id): Path<String>,
+    Json(payload): Json<CreateInvite>,
+) -> Result<CustomResponse<PublicInvite>, Error> {
+    let (household, role) = find_household(&db, &user, id).await?;
+    require_owner(role)?;
+
+    let household_id = household.id.unwrap();
+    let email = payload.email;
+    let invitee = User::find_one(&db, Filter::new().eq(User::EMAIL, &email), None).await?;
+    let is_member = invitee
+        .and_then(|invitee| invitee.id)
+        .is_some_and(|invitee| household.role_of(&invitee).is_some());
+    if is_member {
+        debug!("Invited user is already a member, returning 409 status code");
+        return Err(Error::Conflict(format!(
+            "{} is already a member of the household",
+            email
+        )));
+    }
+
+    let invite = Invite::new(
+        household_id,
+        email,
+        payload.role,
+        user.id,
+        settings.households.invite_ttl_days,
+    )?;
+    invite.validate().map_err(|_error| Error::bad_request())?;
+
+    // An upsert replaces the previous invite for the email, concurrent invites
+    // can't both insert one.
+    let filter = Filter::new()
+        .eq(Invite::HOUSEHOLD, household_id)
+        .eq(Invite::EMAIL, &invite.email);
+    let update = Update::new()
+        .set(Invite::ROLE, invite.role)
+        .set(Invite::INVITED_BY, invite.invited_by)
+        .set(Invite::EXPIRES_AT, invite.expires_at)
+        .set(Invite::CREATED_AT, invite.created_at);
+    let options = UpdateOptions::builder().upsert(true).build();
+    Invite::update_one(&db, filter.clone(), update, options).await?;
+    let Some(invite) = Invite::find_one(&db, filter, None).await? else {
+        debug!("Invite replaced concurrently, returning 409 status code");
+        return Err(Error::Conflict(format!(
+            "{} was invited again meanwhile",
+            invite.email
+        )));
+    };
+
+    let res = CustomResponseBuilder::new()
+        .body(PublicInvite::from(invite))
+        .status_code(StatusCode::CREATED)
+        .build();
+
+    debug!("Returning created invite");
+    Ok(res)
+}
+
+async fn query_invites(user: TokenUser, Db(db): Db) -> Response<Vec<PublicInvite>> {
+    let filter = Filter::new()
+        .eq(Invite::EMAIL, &user.email)
+        .gt(Invite::EXPIRES_AT, date::now());
+    let options = FindOptions::builder()
+        .sort(Sort::new().asc(Invite::CREATED_AT).asc(Invite::ID))
+        .build();
+    let invites = Invite::find(&db, filter, options)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<PublicInvite>>();
+
+    let res = CustomResponseBuilder::new().body(invites).build();
+
+    debug!("Returning invites");
+    Ok(res)
+}
+
+async fn accept_invite(
+    user: TokenUser,
+    Db(db): Db,
+    State(settings): State<Arc<Settings>>,
+    Path(id): Path<String>,
+) -> Result<Json<PublicHousehold>, Error> {
+    let invite_id = to_object_id(id)?;
+    let filter = Filter::new()
+        .eq(Invite::ID, invite_id)
+        .eq(Invite::EMAIL, &user.email)
+        .gt(Invite::EXPIRES_AT, date::now());
+    let Some(invite) = Invite::find_one(&db, filter, None).await? else {
+        debug!("Invite not found, returning 404 status code");
+        return Err(Error::not_found());
+    };
+
+    // The `$ne` condition keeps concurrent accepts from adding the user twice,
+    // and the `$exists` one from going over the member limit.
+    let max_members = settings.households.max_members;
+    let last_member = format!("members.{}", max_members - 1);
+    let filter = Filter::<Household>::new()
+        .eq(Household::ID, invite.household)
+        .merge(doc! { "members.user": { "$ne": user.id } })
+        .merge(doc! { last_member: { "$exists": false } });
+    let update = Update::new()
+        .push(Household::MEMBERS, Member::new(user.id, invite.role))
+        .set(Household::UPDATED_AT, date::now());
+    let Some(household) = Household::find_one_and_update(&db, filter, update).await? else {
+        let Some(household) = Household::find_by_id(&db, &invite.household).await? else {
+            debug!("Household not found, returning 404 status code");
+            return Err(Error::not_found());
+        };
+
+        if household.role_of(&user.id).is_some() {
+            Invite::delete_one(&db, Filter::new().eq(Invite::ID, invite.id)).await?;
+            debug!("User is already a member, returning 409 status code");
+            return Err(Error::Conflict(
+                "you are already a member of the household".to_owned(),
+            ));
+        }
+
+        // The invite is kept, it can be accepted once someone leaves.
+        debug!("Household is full, returning 409 status code");
+        return Err(Error::Conflict(format!(
+            "the household already has {} members",
+            max_members
+        )));
+    };
+
+    Invite::delete_one(&db, Filter::new().eq(Invite::ID, invite.id)).await?;
+
+    debug!("Invite accepted, returning household");
+    Ok(Json(PublicHousehold::from(household)))
+}
+
+// Owners remove any member, other members can only leave. The cats they
+// added stay in the household and they lose access to them.
+async fn remove_member_by_id(
+    user: TokenUser,
+    Db(db): Db,
+    Path((id, user_id)): Path<(String, String)>,
+) -> Result<CustomResponse<()>, Error> {
+    let (household, role) = find_household(&db, &user, id).await?;
+    let member_id = to_object_id(user_id)?;
+    if member_id != user.id {
+        require_owner(role)?;
+    }
+
+    let Some(member_role) = household.role_of(&member_id) else {
+        debug!("Member not found, returning 404 status code");
+        return Err(Error::not_found());
+    };
+
+    // Matches only while another owner remains, so the household is never
+    // left without one.
+    let mut filter = Filter::<Household>::new()
+        .eq(Household::ID, household.id)
+        .merge(doc! { "members.user": member_id });
+    if member_role == Role::Owner {
+        filter = filter.merge(doc! {
+            "members": {
+                "$elemMatch": { "user": { "$ne": member_id }, "role": Role::Owner.as_str() }
+            }
+        });
+    }
+    let update = Update::new()
+        .pull(Household::MEMBERS, doc! { "user": member_id })
+        .set(Household::UPDATED_AT, date::now());
+
+    if Household::find_one_and_update(&db, filter, update)
+        .await?
+        .is_none()
+    {
+        debug!("Removing the last owner, returning 409 status code");
+        return Err(Error::Conflict(
+            "the household needs another owner first".to_owned(),
+        ));
+    }
+
+    let res = CustomResponseBuilder::new()
+        .status_code(StatusCode::NO_CONTENT)
+        .build();
+
+    debug!("Member removed, returning 204 status code");
+    Ok(res)
+}
+
+// Households the user doesn't belong to are reported as missing.
+async fn find_household(
+    db: &Database,
+    user: &TokenUser,
+    id: String,
+) -> Result<(Household, Role), Error> {
+    let household_id = to_object_id(id)?;
+    let household = Household::find_by_id(db, &household_id).await?;
+
+    match household.and_then(|household| Some((household.role_of(&user.id)?, household))) {
+        Some((role, household)) => Ok((household, role)),
+        None => {
+            debug!("Household not found, returning 404 status code");
+            Err(Error::not_found())
+        }
+    }
+}
+
+fn require_owner(role: Role) -> Result<(), Error> {
+    if role == Role::Owner {
+        return Ok(());
+    }
+
+    debug!("User is not a household owner, returning 403 status code");
+    Err(Error::Forbidden(
+        "only household owners can manage members and invites".to_owned(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct CreateHousehold {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CreateInvite {
+    email: String,
+    #[serde(default = "default_invite_role")]
+    role: Role,
+}
+
+fn default_invite_role() -> Role {
+    Role::Member
+}
@@ -1,4 +1,8 @@
-pub mod public;
 pub mod cat;
+pub mod cat_event;
+pub mod cat_transfer;
+pub mod household;
+pub mod photo;
+pub mod public;
 pub mod status;
 pub mod user;
@@ -0,0 +1,312 @@
+use axum::extract::multipart::MultipartError;
+use axum::extract::{DefaultBodyLimit, Multipart, Path, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response as AxumResponse};
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
+use axum_extra::headers::IfNoneMatch;
+use axum_extra::TypedHeader;
+use bytes::{Bytes, BytesMut};
+use std::sync::Arc;
+use tracing::{debug, error};
+use wither::mongodb::options::FindOptions;
+use wither::mongodb::Database;
+
+use crate::blob_store::BlobStore;
+use crate::database::Db;
+use crate::errors::Error;
+use crate::models::cat_event::CatEvent;
+use crate::models::photo::{Photo, PublicPhoto};
+use crate::settings::{Photos, Settings};
+use crate::state::AppState;
+use crate::utils::cat_access::{CatAccess, Permission};
+use crate::utils::custom_response::CustomResponseResult as Response;
+use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
+use crate::utils::etag;
+use crate::utils::images;
+use crate::utils::models::ModelExt;
+use crate::utils::query::{Filter, Sort, Update};
+use crate::utils::to_object_id::to_object_id;
+
+// Photos never change once uploaded, a new upload gets a new URL. They still
+// need a token, so shared caches must not keep them.
+const CACHE_CONTROL: &str = "private, max-age=31536000, immutable";
+
+// Multipart field holding the image.
+const PHOTO_FIELD: &str = "photo";
+
+// Room for the multipart boundaries and headers, and for any other small field
+// sent along with the photo.
+const UPLOAD_HEADROOM: u64 = 64 * 1024;
+
+pub fn create_route(photos: &Photos) -> Router<AppState> {
+    // The photo field is checked against `photos.max_bytes` while reading, the
+    // body limit caps everything else in the upload.
+    let body_limit = photos.max_bytes.saturating_add(UPLOAD_HEADROOM);
+    let body_limit = usize::try_from(body_limit).unwrap_or(usize::MAX);
+
+    Router::new()
+        .route(
+            "/cats/:id/photos",
+            post(upload_photo).layer(DefaultBodyLimit::max(body_limit)),
+        )
+        .route("/cats/:id/photos", get(query_photos))
+        .route("/cats/:id/photos/:photo_id", get(get_photo))
+        .route("/cats/:id/photos/:photo_id", delete(remove_photo))
+        .route("/cats/:id/photos/:photo_id/thumbnail", get(get_thumbnail))
+}
+
+async fn upload_photo(
+    access: CatAccess,
+    Db(db): Db,
+    State(settings): State<Arc<Settings>>,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path(id): Path<String>,
+    mut multipart: Multipart,
+) -> Result<CustomResponse<PublicPhoto>, Error> {
+    let cat = access.find_cat(&db, id, Permission::Write).await?;
+
+    let bytes = read_photo(&mut multipart, settings.photos.max_bytes).await?;
+    let processed = tokio::task::spawn_blocking({
+        let bytes = bytes.clone();
+        let settings = settings.photos.clone();
+        move || images::process(&bytes, &settings)
+    })
+    .await??;
+
+    let photo = Photo::new(
+        access.user.id,
+        cat.id.unwrap(),
+        processed.content_type.to_owned(),
+        bytes.len() as i64,
+        (processed.width, processed.height),
+    );
+
+    // Blobs first, a failure leaves unreferenced blobs rather than a photo
+    // without its image.
+    blobs
+        .put(&photo.blob_key(), bytes, processed.content_type)
+        .await?;
+    blobs
+        .put(
+            &photo.thumbnail_key(),
+            processed.thumbnail.into(),
+            images::THUMBNAIL_CONTENT_TYPE,
+        )
+        .await?;
+
+    let photo = match Photo::create(&db, photo.clone()).await {
+        Ok(photo) => photo,
+        Err(err) => {
+            delete_blobs(blobs.as_ref(), &photo).await;
+            return Err(err);
+        }
+    };
+
+    let res = CustomResponseBuilder::new()
+        .body(PublicPhoto::from(photo))
+        .status_code(StatusCode::CREATED)
+        .build();
+
+    debug!("Returning uploaded photo");
+    Ok(res)
+}
+
+async fn query_photos(
+    access: CatAccess,
+    Db(db): Db,
+    Path(id): Path<String>,
+) -> Response<Vec<PublicPhoto>> {
+    let cat = access.find_cat(&db, id, Permission::Read).await?;
+
+    let options = FindOptions::builder()
+        .sort(Sort::new().asc(Photo::CREATED_AT).asc(Photo::ID))
+        .build();
+    let photos = Photo::find(&db, Filter::new().eq(Photo::CAT, cat.id), options)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<PublicPhoto>>();
+
+    let res = CustomResponseBuilder::new().body(photos).build();
+
+    debug!("Returning photos");
+    Ok(res)
+}
+
+async fn get_photo(
+    access: CatAccess,
+    Db(db): Db,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path((id, photo_id)): Path<(String, String)>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> Result<AxumResponse, Error> {
+    let photo = find_photo(&db, &access, id, photo_id, Permission::Read).await?;
+    let content_type = photo.content_type.clone();
+    let key = photo.blob_key();
+
+    serve(blobs.as_ref(), &photo, &key, &content_type, if_none_match).await
+}
+
+async fn get_thumbnail(
+    access: CatAccess,
+    Db(db): Db,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path((id, photo_id)): Path<(String, String)>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> Result<AxumResponse, Error> {
+    let photo = find_photo(&db, &access, id, photo_id, Permission::Read).await?;
+    let key = photo.thumbnail_key();
+
+    serve(
+        blobs.as_ref(),
+        &photo,
+        &key,
+        images::THUMBNAIL_CONTENT_TYPE,
+        if_none_match,
+    )
+    .await
+}
+
+async fn remove_photo(
+    access: CatAccess,
+    Db(db): Db,
+    State(blobs): State<Arc<dyn BlobStore>>,
+    Path((id, photo_id)): Path<(String, String)>,
+) -> Result<CustomResponse<()>, Error> {
+    let photo = find_photo(&db, &access, id, photo_id, Permission::Write).await?;
+
+    // The document goes first, so the photo disappears even if deleting its
+    // blobs fails.
+    Photo::delete_one(&db, Filter::new().eq(Photo::ID, photo.id)).await?;
+    delete_blobs(blobs.as_ref(), &photo).await;
+
+    // Events attached to the photo are kept, without the attachment.
+    CatEvent::update_many(
+        &db,
+        Filter::new().eq(CatEvent::ATTACHMENT, photo.id),
+        Update::new().unset(CatEvent::ATTACHMENT),
+        None,
+    )
+    .await?;
+
+    let res = CustomResponseBuilder::new()
+        .status_code(StatusCode::NO_CONTENT)
+        .build();
+
+    debug!("Photo deleted, returning 204 status code");
+    Ok(res)
+}
+
+// Reads the photo field, failing as soon as it grows past `max_bytes` instead
+// of buffering the whole upload.
+async fn read_photo(multipart: &mut Multipart, max_bytes: u64) -> Result<Bytes, Error> {
+    let mut field = loop {
+        let field = multipart
+            .next_field()
+            .await
+            .map_err(|err| invalid_upload(err, max_bytes))?;
+        match field {
+            Some(field) if field.name() == Some(PHOTO_FIELD) => break field,
+            Some(_) => continue,
+            None => {
+                return Err(Error::InvalidUpload(format!(
+                    "missing the {} field",
+                    PHOTO_FIELD
+                )))
+            }
+        }
+    };
+
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|err| invalid_upload(err, max_bytes))?
+    {
+        if (bytes.len() + chunk.len()) as u64 > max_bytes {
+            debug!("Photo is too large, returning 413 status code");
+            return Err(Error::PayloadTooLarge(max_bytes));
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(bytes.freeze())
+}
+
+// Other fields too large for the body limit are reported like a large photo.
+fn invalid_upload(err: MultipartError, max_bytes: u64) -> Error {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        debug!("Upload is too large, returning 413 status code");
+        return Error::PayloadTooLarge(max_bytes);
+    }
+
+    Error::InvalidUpload(err.body_text())
+}
+
+async fn find_photo(
+    db: &Database,
+    access: &CatAccess,
+    id: String,
+    photo_id: String,
+    permission: Permission,
+) -> Result<Photo, Error> {
+    // Photos of trashed cats are hidden along with the cat.
+    let cat = access.find_cat(db, id, permission).await?;
+    let photo_id = to_object_id(photo_id)?;
+    let filter = Filter::new().eq(Photo::ID, photo_id).eq(Photo::CAT, cat.id);
+
+    match Photo::find_one(db, filter, None).await? {
+        Some(photo) => Ok(photo),
+        None => {
+            debug!("Photo not found, returning 404 status code");
+            Err(Error::not_found())
+        }
+    }
+}
+
+async fn serve(
+    blobs: &dyn BlobStore,
+    photo: &Photo,
+    key: &str,
+    content_type: &str,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> Result<AxumResponse, Error> {
+    let etag = TypedHeader(etag::from_date(photo.created_at));
+    let cache_control = [(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(CACHE_CONTROL),
+    )];
+    if etag::is_not_modified(if_none_match.as_deref(), photo.created_at) {
+        debug!("Photo not modified, returning 304 status code");
+        return Ok((StatusCode::NOT_MODIFIED, etag, cache_control).into_response());
+    }
+
+    let Some(bytes) = blobs.get(key).await? else {
+        error!("Blob {} of photo {} is missing", key, photo.id.unwrap());
+        return Err(Error::not_found());
+    };
+
+    let content_type = HeaderValue::from_str(content_type)
+        .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream"));
+
+    debug!("Returning photo");
+    Ok((
+        etag,
+        cache_control,
+        [(header::CONTENT_TYPE, content_type)],
+        bytes,
+    )
+        .into_response())
+}
+
+async fn delete_blobs(blobs: &dyn BlobStore, photo: &Photo) {
+    for key in [photo.blob_key(), photo.thumbnail_key()] {
+        if let Err(err) = blobs.delete(&key).await {
+            error!("Failed to delete blob {}: {}", key, err);
+        }
+    }
+}
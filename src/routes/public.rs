@@ -7,8 +7,9 @@ use axum::{
 use serde_json::json;
 
 use crate::pages;
+use crate::state::AppState;
 
-pub fn create_route() -> Router {
+pub fn create_route() -> Router<AppState> {
     Router::new()
         .route("/", get(home_page))
         .route("/info", get(info_page))
@@ -4,8 +4,9 @@ use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use crate::errors::Error;
+use crate::state::AppState;
 
-pub fn create_route() -> Router {
+pub fn create_route() -> Router<AppState> {
     Router::new().route("/status", get(get_status))
 }
 
@@ -1,27 +1,32 @@
 use axum::http::StatusCode;
-use axum::{routing::post, Json, Router};
-use bson::doc;
+use axum::{extract::State, routing::post, Json, Router};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use tracing::debug;
 
+use crate::database::Db;
 use crate::errors::{AuthenticateError, Error};
 use crate::models::user;
 use crate::models::user::{PublicUser, User};
-use crate::settings::SETTINGS;
+use crate::state::AppState;
 use crate::utils::custom_response::{CustomResponse, CustomResponseBuilder};
 use crate::utils::models::ModelExt;
-use crate::utils::token;
+use crate::utils::query::Filter;
+use crate::utils::token::{self, Keys};
 
-pub fn create_route() -> Router {
+pub fn create_route() -> Router<AppState> {
     Router::new()
         .route("/users", post(create_user))
         .route("/users/authenticate", post(authenticate_user))
 }
 
-async fn create_user(Json(body): Json<CreateBody>) -> Result<CustomResponse<PublicUser>, Error> {
+async fn create_user(
+    Db(db): Db,
+    Json(body): Json<CreateBody>,
+) -> Result<CustomResponse<PublicUser>, Error> {
     let password_hash = user::hash_password(body.password).await?;
     let user = User::new(body.name, body.email, password_hash);
-    let user = User::create(user).await?;
+    let user = User::create(&db, user).await?;
     let res = PublicUser::from(user);
 
     let res = CustomResponseBuilder::new()
@@ -33,6 +38,8 @@ async fn create_user(Json(body): Json<CreateBody>) -> Result<CustomResponse<Publ
 }
 
 async fn authenticate_user(
+    Db(db): Db,
+    State(keys): State<Arc<Keys>>,
     Json(body): Json<AuthorizeBody>,
 ) -> Result<Json<AuthenticateResponse>, Error> {
     let email = &body.email;
@@ -48,7 +55,7 @@ async fn authenticate_user(
         return Err(Error::bad_request());
     }
 
-    let user = User::find_one(doc! { "email": email }, None).await?;
+    let user = User::find_one(&db, Filter::new().eq(User::EMAIL, email), None).await?;
 
     let user = match user {
         Some(user) => user,
@@ -68,8 +75,7 @@ async fn authenticate_user(
         return Err(Error::Authenticate(AuthenticateError::Locked));
     }
 
-    let secret = SETTINGS.auth.secret.as_str();
-    let token = token::create(user.clone(), secret)
+    let token = token::create(user.clone(), &keys)
         .map_err(|_| Error::Authenticate(AuthenticateError::TokenCreation))?;
 
     let res = AuthenticateResponse {
@@ -0,0 +1,115 @@
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use listenfd::ListenFd;
+use std::fs::{self, Permissions};
+use std::os::unix::fs::PermissionsExt;
+use std::{fmt, io};
+use tokio::net::{TcpListener, UnixListener};
+use tracing::error;
+
+use crate::settings::{Server, UnixSocket};
+
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Binds the listener described by the server settings. A socket passed
+    /// down by systemd (or any other supervisor using the `LISTEN_FDS`
+    /// protocol) takes precedence over the configured address.
+    pub async fn bind(settings: &Server) -> io::Result<Self> {
+        let mut listenfd = ListenFd::from_env();
+        if listenfd.len() > 0 {
+            return Self::inherit(&mut listenfd);
+        }
+
+        if let Some(socket) = &settings.unix_socket {
+            return Self::bind_unix(socket);
+        }
+
+        let listener = TcpListener::bind((settings.host(), settings.port)).await?;
+        Ok(Self::Tcp(listener))
+    }
+
+    fn inherit(listenfd: &mut ListenFd) -> io::Result<Self> {
+        if let Ok(Some(listener)) = listenfd.take_tcp_listener(0) {
+            listener.set_nonblocking(true)?;
+            return Ok(Self::Tcp(TcpListener::from_std(listener)?));
+        }
+
+        match listenfd.take_unix_listener(0)? {
+            Some(listener) => {
+                listener.set_nonblocking(true)?;
+                Ok(Self::Unix(UnixListener::from_std(listener)?))
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "LISTEN_FDS is set but no listening socket was passed",
+            )),
+        }
+    }
+
+    fn bind_unix(socket: &UnixSocket) -> io::Result<Self> {
+        // A socket file left behind by a previous run would make bind fail.
+        match fs::remove_file(&socket.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+
+        let listener = UnixListener::bind(&socket.path)?;
+
+        if let Some(mode) = socket.mode() {
+            let mode = mode.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            fs::set_permissions(&socket.path, Permissions::from_mode(mode))?;
+        }
+
+        Ok(Self::Unix(listener))
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Listener::Tcp(listener) => match listener.local_addr() {
+                Ok(address) => write!(f, "http://{}", address),
+                Err(_) => write!(f, "tcp:<unknown>"),
+            },
+            Listener::Unix(listener) => match listener.local_addr() {
+                Ok(address) => match address.as_pathname() {
+                    Some(path) => write!(f, "unix:{}", path.display()),
+                    None => write!(f, "unix:<unnamed>"),
+                },
+                Err(_) => write!(f, "unix:<unknown>"),
+            },
+        }
+    }
+}
+
+pub async fn serve(listener: Listener, app: Router) -> io::Result<()> {
+    match listener {
+        Listener::Tcp(listener) => axum::serve(listener, app).await,
+        Listener::Unix(listener) => serve_unix(listener, app).await,
+    }
+}
+
+// axum::serve only accepts TCP listeners, Unix sockets are served by driving
+// hyper directly.
+async fn serve_unix(listener: UnixListener, app: Router) -> io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let service = TowerToHyperService::new(app.clone());
+
+        tokio::spawn(async move {
+            let io = TokioIo::new(stream);
+            if let Err(err) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(io, service)
+                .await
+            {
+                error!("Error serving unix socket connection: {}", err);
+            }
+        });
+    }
+}
@@ -0,0 +1,73 @@
+use axum::extract::FromRef;
+use std::sync::Arc;
+use wither::mongodb::Database;
+
+use crate::blob_store::{self, BlobStore};
+use crate::cors::AllowedOrigins;
+use crate::database::{self, Readiness};
+use crate::errors::Error;
+use crate::settings::Settings;
+use crate::utils::token::Keys;
+
+// Everything a request handler needs that used to live in process globals.
+// Cloning is cheap: the settings and keys are reference counted and the
+// MongoDB handle wraps a shared connection pool.
+#[derive(Clone)]
+pub struct AppState {
+    pub settings: Arc<Settings>,
+    pub db: Database,
+    pub db_ready: Readiness,
+    pub keys: Arc<Keys>,
+    pub allowed_origins: AllowedOrigins,
+    pub blobs: Arc<dyn BlobStore>,
+}
+
+impl AppState {
+    pub async fn new(settings: Settings) -> Result<Self, Error> {
+        // The client connects lazily, the database is initialized in the
+        // background by `app::create_app`.
+        let db = database::client(&settings.database).await?;
+        let keys = Keys::new(settings.auth.secret.expose().as_bytes());
+        let allowed_origins = AllowedOrigins::new(&settings.cors.allowed_origins);
+        let blobs = blob_store::from_settings(&settings.photos.storage, &db);
+
+        Ok(Self {
+            settings: Arc::new(settings),
+            db,
+            db_ready: Readiness::new(),
+            keys: Arc::new(keys),
+            allowed_origins,
+            blobs,
+        })
+    }
+}
+
+impl FromRef<AppState> for Arc<Settings> {
+    fn from_ref(state: &AppState) -> Self {
+        state.settings.clone()
+    }
+}
+
+impl FromRef<AppState> for Database {
+    fn from_ref(state: &AppState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<AppState> for Readiness {
+    fn from_ref(state: &AppState) -> Self {
+        state.db_ready.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Keys> {
+    fn from_ref(state: &AppState) -> Self {
+        state.keys.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn BlobStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.blobs.clone()
+    }
+}
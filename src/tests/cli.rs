@@ -0,0 +1,85 @@
+use clap::Parser;
+use wither::mongodb::Database;
+
+use crate::cli::{self, Cli, Command, CreateUserArgs};
+use crate::models::user::User;
+use crate::tests::setup::{test_settings, use_app};
+use crate::utils::models::ModelExt;
+use crate::utils::query::Filter;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+fn user_args(email: &str) -> CreateUserArgs {
+    CreateUserArgs {
+        name: "Nahuel".to_owned(),
+        email: email.to_owned(),
+        password: "Password1".to_owned(),
+    }
+}
+
+async fn find_user(db: &Database, email: &str) -> User {
+    User::find_one(db, Filter::new().eq(User::EMAIL, email), None)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn cli_parses_commands() {
+    let cli = Cli::try_parse_from([
+        "rustapi",
+        "create-user",
+        "--name",
+        "Nahuel",
+        "--email",
+        "nahuel@gmail.com",
+        "--password",
+        "Password1",
+    ])
+    .unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::CreateUser(args)) if args.name == "Nahuel" && args.email == "nahuel@gmail.com"
+    ));
+
+    let cli = Cli::try_parse_from(["rustapi", "serve", "--port", "4000"]).unwrap();
+    assert!(matches!(cli.command, Some(Command::Serve(args)) if args.port == Some(4000)));
+
+    let cli = Cli::try_parse_from(["rustapi"]).unwrap();
+    assert!(cli.command.is_none(), "Serves by default");
+
+    assert!(Cli::try_parse_from(["rustapi", "lock-user"]).is_err());
+}
+
+#[test]
+fn user_commands() {
+    use_app(|app| async move {
+        let settings = test_settings();
+        cli::create_user(&settings, user_args("nahuel@gmail.com"))
+            .await
+            .unwrap();
+        assert_eq!(find_user(app.db(), "nahuel@gmail.com").await.name, "Nahuel");
+
+        cli::set_locked(&settings, "nahuel@gmail.com", true)
+            .await
+            .unwrap();
+        assert!(find_user(app.db(), "nahuel@gmail.com")
+            .await
+            .locked_at
+            .is_some());
+
+        cli::set_locked(&settings, "nahuel@gmail.com", false)
+            .await
+            .unwrap();
+        assert_eq!(
+            find_user(app.db(), "nahuel@gmail.com").await.locked_at,
+            None
+        );
+
+        let err = cli::set_locked(&settings, "nobody@gmail.com", true)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "No user found with email nobody@gmail.com");
+    });
+}
@@ -0,0 +1,118 @@
+use bson::doc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::database;
+use crate::errors::Error;
+use crate::models::cat::Cat;
+use crate::tests::setup::use_app;
+use crate::tests::utils::{create_user, supports_transactions};
+use crate::utils::models::ModelExt;
+use crate::utils::query::{Filter, Update};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn with_transaction_commits_or_aborts() {
+    use_app(|app| async move {
+        if !supports_transactions(app.db()).await {
+            return;
+        }
+
+        let user = create_user(app.db(), "nahuel@gmail.com").await.unwrap();
+        let user_id = user.id.unwrap();
+        let db = app.db().clone();
+
+        let created = database::with_transaction(&db, |session| {
+            let db = db.clone();
+            Box::pin(async move {
+                let cat = Cat::new(user_id, "Nacho".to_owned());
+                Cat::create_with_session(&db, cat, session).await
+            })
+        })
+        .await
+        .unwrap();
+        assert!(Cat::find_by_id(&db, &created.id.unwrap())
+            .await
+            .unwrap()
+            .is_some());
+
+        let result = database::with_transaction(&db, |session| {
+            let db = db.clone();
+            Box::pin(async move {
+                let cat = Cat::new(user_id, "Tigre".to_owned());
+                Cat::create_with_session(&db, cat, session).await?;
+                Err::<(), _>(Error::PreconditionFailed)
+            })
+        })
+        .await;
+        assert!(matches!(result, Err(Error::PreconditionFailed)));
+
+        let count = Cat::count(&db, doc! { "name": "Tigre" }).await.unwrap();
+        assert_eq!(count, 0, "The failed transaction is rolled back");
+    });
+}
+
+#[test]
+fn with_transaction_retries_write_conflicts() {
+    use_app(|app| async move {
+        if !supports_transactions(app.db()).await {
+            return;
+        }
+
+        let user = create_user(app.db(), "nahuel@gmail.com").await.unwrap();
+        let cat = Cat::new(user.id.unwrap(), "Nacho".to_owned());
+        let cat_id = Cat::create(app.db(), cat).await.unwrap().id;
+        let db = app.db().clone();
+
+        // An open transaction holding the cat makes the first attempt fail with
+        // a write conflict, labeled as a transient transaction error.
+        let mut blocker = db.client().start_session(None).await.unwrap();
+        blocker.start_transaction(None).await.unwrap();
+        Cat::update_one_with_session(
+            &db,
+            Filter::new().eq(Cat::ID, cat_id),
+            Update::new().set(Cat::NAME, "Tigre"),
+            None,
+            &mut blocker,
+        )
+        .await
+        .unwrap();
+
+        let blocker = Arc::new(Mutex::new(Some(blocker)));
+        let attempts = Arc::new(AtomicU32::new(0));
+        database::with_transaction(&db, |session| {
+            let db = db.clone();
+            let blocker = blocker.clone();
+            let attempts = attempts.clone();
+            Box::pin(async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                let result = Cat::update_one_with_session(
+                    &db,
+                    Filter::new().eq(Cat::ID, cat_id),
+                    Update::new().set(Cat::NAME, "Cielito"),
+                    None,
+                    session,
+                )
+                .await;
+
+                if let Some(mut blocker) = blocker.lock().await.take() {
+                    blocker.abort_transaction().await?;
+                }
+
+                result.map(|_| ())
+            })
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        let cat = Cat::find_by_id(&db, &cat_id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cat.name, "Cielito");
+    });
+}
@@ -0,0 +1,118 @@
+use bson::{doc, oid::ObjectId};
+use std::time::Duration;
+use wither::mongodb::IndexModel;
+use wither::Model;
+
+use crate::database;
+use crate::errors::{Error, MigrationError};
+use crate::models;
+use crate::models::cat::Cat;
+use crate::settings::{IndexMode, Indexes};
+use crate::tests::setup::{spawn_app, test_settings, use_app};
+use crate::utils::models::ModelExt;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn verify_mode_reports_undeclared_indexes() {
+    use_app(|app| async move {
+        let index = IndexModel::builder().keys(doc! { "name": 1 }).build();
+        Cat::collection(app.db())
+            .create_index(index, None)
+            .await
+            .unwrap();
+
+        let drift = Cat::index_drift(app.db()).await.unwrap();
+        assert_eq!(drift.missing.len(), 0);
+        assert_eq!(drift.extra, vec!["name_1".to_owned()]);
+
+        let settings = Indexes {
+            mode: IndexMode::Verify,
+            background: false,
+            fail_on_drift: true,
+        };
+        let result = models::sync_indexes(app.db(), &settings).await;
+        assert!(result.is_err());
+
+        let settings = Indexes {
+            mode: IndexMode::Sync,
+            ..settings
+        };
+        models::sync_indexes(app.db(), &settings).await.unwrap();
+
+        let drift = Cat::index_drift(app.db()).await.unwrap();
+        assert!(drift.is_empty());
+    });
+}
+
+#[test]
+fn sync_and_verify_on_an_empty_database() {
+    use_app(|app| async move {
+        let name = format!("rustapi_test_{}", ObjectId::new().to_hex());
+        let db = app.db().client().database(&name);
+
+        // Collections that don't exist yet miss every declared index.
+        let drifts = models::index_drift(&db).await.unwrap();
+        assert!(!drifts.is_empty());
+        assert!(drifts.iter().all(|drift| drift.extra.is_empty()));
+
+        let settings = Indexes {
+            mode: IndexMode::Sync,
+            background: false,
+            fail_on_drift: true,
+        };
+        let synced = models::sync_indexes(&db, &settings).await;
+
+        let settings = Indexes {
+            mode: IndexMode::Verify,
+            ..settings
+        };
+        let verified = models::sync_indexes(&db, &settings).await;
+
+        db.drop(None).await.unwrap();
+        synced.unwrap();
+        verified.unwrap();
+    });
+}
+
+#[test]
+fn server_stops_initializing_when_indexes_drifted() {
+    use_app(|app| async move {
+        let index = IndexModel::builder().keys(doc! { "name": 1 }).build();
+        Cat::collection(app.db())
+            .create_index(index, None)
+            .await
+            .unwrap();
+
+        let mut settings = test_settings();
+        settings.database.indexes = Indexes {
+            mode: IndexMode::Verify,
+            background: false,
+            fail_on_drift: true,
+        };
+        let other = spawn_app(settings).await;
+        let failed =
+            tokio::time::timeout(Duration::from_secs(10), other.state.db_ready.failed()).await;
+
+        Cat::collection(app.db())
+            .drop_index("name_1", None)
+            .await
+            .unwrap();
+        assert!(failed.unwrap().contains("drifted"));
+        assert!(!other.state.db_ready.is_ready());
+    });
+}
+
+#[test]
+fn only_connection_errors_are_transient() {
+    assert!(!database::is_transient(&Error::IndexDrift(
+        "name_1".to_owned()
+    )));
+    assert!(!database::is_transient(&Error::Migration(
+        MigrationError::Locked("other".to_owned())
+    )));
+    assert!(!database::is_transient(&Error::Migration(
+        MigrationError::Irreversible(3)
+    )));
+}
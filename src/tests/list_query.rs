@@ -0,0 +1,75 @@
+use bson::doc;
+
+use crate::models::cat::Cat;
+use crate::models::cat_event::CatEvent;
+use crate::utils::list_query::ListQuery;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn list_query_builds_filter_and_sort() {
+    let query = ListQuery::<Cat>::parse(
+        "name%5Bprefix%5D=T.m&created_at[gte]=2024-01-01T00:00:00Z&created_at[lt]=2024-02-01T00:00:00Z&sort=-updated_at,name&limit=10",
+    )
+    .unwrap();
+
+    let from = bson::DateTime::parse_rfc3339_str("2024-01-01T00:00:00Z").unwrap();
+    let to = bson::DateTime::parse_rfc3339_str("2024-02-01T00:00:00Z").unwrap();
+    assert_eq!(
+        query.filter,
+        doc! {
+            "name": { "$regex": "^T\\.m" },
+            "created_at": { "$gte": from, "$lt": to },
+        }
+    );
+    assert_eq!(
+        query.sort_or_default(),
+        doc! { "updated_at": -1, "name": 1, "_id": 1 }
+    );
+}
+
+#[test]
+fn list_query_defaults_to_newest_first() {
+    let query = ListQuery::<Cat>::parse("").unwrap();
+
+    assert_eq!(query.filter, doc! {});
+    assert_eq!(
+        query.sort_or_default(),
+        doc! { "created_at": -1, "_id": -1 }
+    );
+}
+
+#[test]
+fn list_query_defaults_to_the_model_order() {
+    let query = ListQuery::<CatEvent>::parse("").unwrap();
+
+    assert_eq!(
+        query.sort_or_default(),
+        doc! { "occurred_at": -1, "_id": -1 }
+    );
+}
+
+#[test]
+fn list_query_rejects_empty_sort() {
+    assert!(ListQuery::<Cat>::parse("sort=").is_err());
+    assert!(ListQuery::<Cat>::parse("sort=,").is_err());
+}
+
+#[test]
+fn list_query_builds_equality_filters() {
+    let query = ListQuery::<Cat>::parse("tags[in]=indoor,%20senior&breed[eq]=Siamese").unwrap();
+
+    assert_eq!(
+        query.filter,
+        doc! {
+            "tags": { "$in": ["indoor", "senior"] },
+            "breed": { "$eq": "Siamese" },
+        }
+    );
+
+    // breed is also a text field, tags only accepts equality operators.
+    let query = ListQuery::<Cat>::parse("breed[prefix]=Sia").unwrap();
+    assert_eq!(query.filter, doc! { "breed": { "$regex": "^Sia" } });
+    assert!(ListQuery::<Cat>::parse("tags[prefix]=in").is_err());
+}
@@ -0,0 +1,28 @@
+use crate::migrations;
+use crate::tests::setup::use_app;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+// A single test, migrations share the lock and the _migrations collection.
+#[test]
+fn migrate_up_and_down() {
+    use_app(|app| async move {
+        migrations::up(app.db(), false).await.unwrap();
+
+        let pending = migrations::status(app.db())
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|step| step.applied_at.is_none())
+            .count();
+        assert_eq!(pending, 0);
+
+        let planned = migrations::up(app.db(), true).await.unwrap();
+        assert_eq!(planned.len(), 0);
+
+        // Reverting past an irreversible migration fails before touching anything.
+        let result = migrations::down(app.db(), 0, true).await;
+        assert!(result.is_err());
+    });
+}
@@ -1,3 +1,14 @@
+mod blob_store;
+mod cli;
+mod database;
+mod indexes;
+mod list_query;
+mod migrations;
+mod models;
+mod purge;
+mod query;
+mod reload;
 mod routes;
+mod settings;
 mod setup;
 mod utils;
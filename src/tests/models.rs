@@ -0,0 +1,196 @@
+use bson::doc;
+
+use crate::models::cat::Cat;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::utils::date;
+use crate::utils::models::{ModelExt, WriteModel, WriteOutcome};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn model_operations_with_session() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nahuel@gmail.com").await.unwrap();
+        let user_id = user.id.unwrap();
+        let mut session = app.db().client().start_session(None).await.unwrap();
+
+        let cat = Cat::new(user_id, "Nacho".to_owned());
+        let cat = Cat::create_with_session(app.db(), cat, &mut session)
+            .await
+            .unwrap();
+        assert!(cat.id.is_some());
+
+        let found = Cat::find_by_id_with_session(app.db(), &cat.id.unwrap(), &mut session)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.name, "Nacho");
+
+        let cats = Cat::find_with_session(app.db(), doc! { "user": user_id }, None, &mut session)
+            .await
+            .unwrap();
+        assert_eq!(cats.len(), 1);
+
+        let deleted =
+            Cat::delete_many_with_session(app.db(), doc! { "user": user_id }, &mut session)
+                .await
+                .unwrap();
+        assert_eq!(deleted.deleted_count, 1);
+    });
+}
+
+#[test]
+fn cat_reads_documents_without_details() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nahuel@gmail.com").await.unwrap();
+        let now = bson::DateTime::now();
+
+        // Shape of the documents stored before the v0003 migration.
+        let id = <Cat as wither::Model>::collection(app.db())
+            .insert_one(
+                doc! { "user": user.id.unwrap(), "name": "Nacho", "updated_at": now, "created_at": now },
+                None,
+            )
+            .await
+            .unwrap()
+            .inserted_id;
+
+        let cat = Cat::find_by_id(app.db(), &id.as_object_id().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cat.name, "Nacho");
+        assert_eq!(cat.breed, None);
+        assert!(cat.tags.is_empty());
+        assert!(cat.weights.is_empty());
+    });
+}
+
+#[test]
+fn insert_many_and_bulk_write() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nahuel@gmail.com").await.unwrap();
+        let user_id = user.id.unwrap();
+
+        let cats = vec![
+            Cat::new(user_id, "Nacho".to_owned()),
+            Cat::new(user_id, String::new()),
+            Cat::new(user_id, "Tigrin".to_owned()),
+        ];
+        let results = Cat::insert_many(app.db(), cats).await.unwrap();
+        assert!(results[0].as_ref().unwrap().id.is_some());
+        assert!(results[1].is_err(), "Invalid cats are not inserted");
+        let tigrin = results[2].as_ref().unwrap().clone();
+
+        let operations = vec![
+            WriteModel::InsertOne(Cat::new(user_id, "Tom".to_owned())),
+            WriteModel::UpdateOne {
+                filter: doc! { "_id": tigrin.id }.into(),
+                update: doc! { "$set": { "name": "Tigre" } }.into(),
+            },
+            WriteModel::DeleteOne {
+                filter: doc! { "name": "Nacho" }.into(),
+            },
+        ];
+        let outcomes = Cat::bulk_write(app.db(), operations).await.unwrap();
+        assert!(matches!(outcomes[0], Ok(WriteOutcome::Inserted(_))));
+        assert!(matches!(outcomes[1], Ok(WriteOutcome::Updated)));
+        assert!(matches!(outcomes[2], Ok(WriteOutcome::Deleted)));
+
+        let mut names = Cat::find(app.db(), doc! { "user": user_id }, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|cat| cat.name)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, vec!["Tigre", "Tom"]);
+    });
+}
+
+#[test]
+fn bulk_write_reports_unmatched_operations() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nahuel@gmail.com").await.unwrap();
+        let cat = Cat::new(user.id.unwrap(), "Nacho".to_owned());
+        let cat = Cat::create(app.db(), cat).await.unwrap();
+
+        // The cat changes between the read and the write.
+        let read = doc! { "_id": cat.id, "updated_at": cat.updated_at };
+        Cat::update_one(
+            app.db(),
+            doc! { "_id": cat.id },
+            doc! { "$set": { "name": "Tigrin", "updated_at": date::now() } },
+            None,
+        )
+        .await
+        .unwrap();
+
+        let operations = vec![
+            WriteModel::UpdateOne {
+                filter: read.clone().into(),
+                update: doc! { "$set": { "name": "Tigre" } }.into(),
+            },
+            WriteModel::DeleteOne {
+                filter: read.into(),
+            },
+        ];
+        let outcomes = Cat::bulk_write(app.db(), operations).await.unwrap();
+        assert!(matches!(outcomes[0], Ok(WriteOutcome::Unmatched)));
+        assert!(matches!(outcomes[1], Ok(WriteOutcome::Unmatched)));
+
+        let cat = Cat::find_by_id(app.db(), &cat.id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cat.name, "Tigrin");
+    });
+}
+
+#[test]
+fn bulk_write_keeps_the_order_of_operations() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nahuel@gmail.com").await.unwrap();
+        let user_id = user.id.unwrap();
+        let cat = Cat::create(app.db(), Cat::new(user_id, "Nacho".to_owned()))
+            .await
+            .unwrap();
+
+        let operations = vec![
+            WriteModel::UpdateOne {
+                filter: doc! { "_id": cat.id }.into(),
+                update: doc! { "$set": { "name": "Tigre" } }.into(),
+            },
+            // Only matches once the previous update is applied.
+            WriteModel::UpdateOne {
+                filter: doc! { "_id": cat.id, "name": "Tigre" }.into(),
+                update: doc! { "$set": { "name": "Tom" } }.into(),
+            },
+            WriteModel::DeleteOne {
+                filter: doc! { "_id": cat.id }.into(),
+            },
+            WriteModel::InsertOne(Cat::new(user_id, "Pelusa".to_owned())),
+            // The cat is gone by now.
+            WriteModel::UpdateOne {
+                filter: doc! { "_id": cat.id }.into(),
+                update: doc! { "$set": { "name": "Cielito" } }.into(),
+            },
+        ];
+        let outcomes = Cat::bulk_write(app.db(), operations).await.unwrap();
+        assert!(matches!(outcomes[0], Ok(WriteOutcome::Updated)));
+        assert!(matches!(outcomes[1], Ok(WriteOutcome::Updated)));
+        assert!(matches!(outcomes[2], Ok(WriteOutcome::Deleted)));
+        assert!(matches!(outcomes[3], Ok(WriteOutcome::Inserted(_))));
+        assert!(matches!(outcomes[4], Ok(WriteOutcome::Unmatched)));
+
+        let names = Cat::find(app.db(), doc! { "user": user_id }, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|cat| cat.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Pelusa"]);
+    });
+}
@@ -0,0 +1,78 @@
+use bson::doc;
+use bytes::Bytes;
+use chrono::Duration;
+
+use crate::models::cat::Cat;
+use crate::models::cat_event::{CatEvent, EventKind};
+use crate::models::photo::Photo;
+use crate::purge;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::utils::date::{self, Date};
+use crate::utils::models::ModelExt;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn purge_trash_deletes_expired_cats_with_their_photos_and_events() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let user_id = user.id.unwrap();
+        let blobs = app.state.blobs.as_ref();
+
+        let yesterday = Date::from_chrono(date::now().to_chrono() - Duration::days(1));
+        let tomorrow = Date::from_chrono(date::now().to_chrono() + Duration::days(1));
+
+        let mut expired = Cat::new(user_id, "Tigrin".to_owned());
+        expired.deleted_at = Some(yesterday);
+        expired.purge_at = Some(yesterday);
+        let expired = Cat::create(app.db(), expired).await.unwrap();
+
+        let mut trashed = Cat::new(user_id, "Michi".to_owned());
+        trashed.deleted_at = Some(yesterday);
+        trashed.purge_at = Some(tomorrow);
+        let trashed = Cat::create(app.db(), trashed).await.unwrap();
+
+        let mut photos = Vec::new();
+        for cat in [&expired, &trashed] {
+            let photo = Photo::new(user_id, cat.id.unwrap(), "image/png".to_owned(), 3, (1, 1));
+            let photo = Photo::create(app.db(), photo).await.unwrap();
+            for key in [photo.blob_key(), photo.thumbnail_key()] {
+                blobs
+                    .put(&key, Bytes::from_static(b"png"), "image/png")
+                    .await
+                    .unwrap();
+            }
+            photos.push(photo);
+
+            let event = CatEvent::new(cat.id.unwrap(), user_id, EventKind::Note, yesterday);
+            CatEvent::create(app.db(), event).await.unwrap();
+        }
+
+        let purged = purge::purge_trash(app.db(), blobs).await.unwrap();
+        assert_eq!(purged, 1);
+
+        let cats = Cat::find(app.db(), doc! { "deleted_at": { "$ne": null } }, None)
+            .await
+            .unwrap();
+        assert_eq!(cats.len(), 1);
+        assert_eq!(cats[0].id, trashed.id);
+
+        let remaining = Photo::find(app.db(), doc! {}, None).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, photos[1].id);
+
+        let events = CatEvent::find(app.db(), doc! {}, None).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].cat, trashed.id.unwrap());
+
+        assert!(blobs.get(&photos[0].blob_key()).await.unwrap().is_none());
+        assert!(blobs
+            .get(&photos[0].thumbnail_key())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(blobs.get(&photos[1].blob_key()).await.unwrap().is_some());
+    });
+}
@@ -0,0 +1,116 @@
+use bson::{doc, oid::ObjectId, Document};
+
+use crate::errors::Error;
+use crate::models::cat::Cat;
+use crate::utils::date;
+use crate::utils::query::{Field, Filter, Sort, Update};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn field_names_follow_serde_renames() {
+    assert_eq!(Cat::ID.name(), "_id");
+    assert_eq!(Cat::CREATED_AT.name(), "created_at");
+}
+
+#[test]
+fn filter_builds_documents() {
+    let user = ObjectId::new();
+    let now = date::now();
+
+    let filter: Document = Filter::new()
+        .eq(Cat::USER, user)
+        .gte(Cat::CREATED_AT, now)
+        .lt(Cat::CREATED_AT, now)
+        .merge(doc! { "name": { "$regex": "^N" } })
+        .try_into()
+        .unwrap();
+
+    assert_eq!(
+        filter,
+        doc! {
+            "user": user,
+            "created_at": { "$gte": now, "$lt": now },
+            "name": { "$regex": "^N" },
+        }
+    );
+}
+
+#[test]
+fn merged_conditions_on_the_same_key_all_apply() {
+    let user = ObjectId::new();
+    let filter: Document = Filter::<Cat>::raw(doc! { "$and": [{ "household": null }] })
+        .eq(Cat::NAME, "Nacho")
+        .merge(doc! {
+            "name": { "$ne": "Tigrin" },
+            "$and": [{ "user": user }],
+            "tags": "indoor",
+        })
+        .try_into()
+        .unwrap();
+
+    assert_eq!(
+        filter,
+        doc! {
+            "tags": "indoor",
+            "$and": [
+                { "household": null },
+                { "user": user },
+                { "name": "Nacho" },
+                { "name": { "$ne": "Tigrin" } },
+            ],
+        }
+    );
+}
+
+#[test]
+fn sort_and_update_build_documents() {
+    let sort: Document = Sort::new().desc(Cat::CREATED_AT).asc(Cat::NAME).into();
+    assert_eq!(sort, doc! { "created_at": -1, "name": 1 });
+
+    let update: Document = Update::new()
+        .set(Cat::NAME, "Nacho")
+        .set(Cat::DELETED_AT, None::<date::Date>)
+        .unset(Cat::PURGE_AT)
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        update,
+        doc! {
+            "$set": { "name": "Nacho", "deleted_at": null },
+            "$unset": { "purge_at": "" },
+        }
+    );
+}
+
+#[test]
+fn update_pushes_and_pulls_array_elements() {
+    let update: Document = Update::new()
+        .push(Cat::TAGS, "indoor")
+        .pull(Cat::TAGS, doc! { "$eq": "outdoor" })
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        update,
+        doc! {
+            "$push": { "tags": "indoor" },
+            "$pull": { "tags": { "$eq": "outdoor" } },
+        }
+    );
+}
+
+#[test]
+fn values_that_dont_serialize_fail_the_conversion() {
+    // BSON has no unsigned 64 bit integers.
+    let size = Field::<Cat, u64>::new("size");
+
+    let filter: Result<Document, Error> = Filter::new()
+        .eq(Cat::NAME, "Nacho")
+        .gt(size, u64::MAX)
+        .try_into();
+    assert!(matches!(filter, Err(Error::SerializeQuery(_))));
+
+    let update: Result<Document, Error> = Update::new().set(size, u64::MAX).try_into();
+    assert!(matches!(update, Err(Error::SerializeQuery(_))));
+}
@@ -0,0 +1,55 @@
+use serde_json::json;
+
+use crate::reload::{diff, ignored};
+use crate::settings::Settings;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn diff_lists_changed_settings() {
+    let before = Settings::default();
+    let mut after = Settings::default();
+    after.logger.level = "debug".to_owned();
+    after.cors.allowed_origins = vec!["https://example.com".to_owned()];
+
+    let changes = diff(&before, &after);
+
+    let keys = changes
+        .iter()
+        .map(|change| change.key.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec!["cors.allowed_origins", "logger.level"]);
+
+    let level = changes
+        .iter()
+        .find(|change| change.key == "logger.level")
+        .unwrap();
+    assert_eq!(level.from, json!("info"));
+    assert_eq!(level.to, json!("debug"));
+}
+
+#[test]
+fn diff_never_exposes_secrets() {
+    let before = Settings::default();
+    let mut after = Settings::default();
+    after.auth.secret = "hunter2".into();
+
+    let changes = diff(&before, &after);
+    assert!(changes.is_empty(), "Secrets should be compared redacted");
+}
+
+#[test]
+fn ignored_lists_settings_that_need_a_restart() {
+    let before = Settings::default();
+    let mut after = Settings::default();
+    after.logger.level = "debug".to_owned();
+    after.server.port = before.server.port + 1;
+    after.pagination.max_limit = before.pagination.max_limit + 1;
+
+    let changes = diff(&before, &after);
+    assert_eq!(
+        ignored(&changes),
+        vec!["pagination.max_limit", "server.port"]
+    );
+}
@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::models::cat::Cat;
 use crate::models::cat::PublicCat;
-use crate::tests::setup::use_app;
+use crate::tests::setup::{spawn_app, test_settings, use_app};
 use crate::tests::utils::create_user;
 use crate::tests::utils::create_user_token;
+use crate::tests::utils::supports_transactions;
 use crate::utils::models::ModelExt;
 
 #[cfg(test)]
@@ -23,13 +24,15 @@ fn post_cat_route() {
         name: "Tigrin".to_owned(),
     };
 
-    use_app(async move {
-        let user = create_user("nico@test.com").await.unwrap();
-        let token = create_user_token(user.clone()).await.unwrap();
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
 
         let client = reqwest::Client::new();
         let res = client
-            .post("http://localhost:8088/v1/cats")
+            .post(app.url("/v1/cats"))
             .header("Authorization", format!("Bearer {}", token))
             .json(&body)
             .send()
@@ -49,21 +52,87 @@ fn post_cat_route() {
     });
 }
 
+#[test]
+fn post_cat_route_with_details() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(app.url("/v1/cats"))
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({
+                "name": "Tigrin",
+                "breed": "Siamese",
+                "birthdate": "2020-05-01T00:00:00Z",
+                "sex": "male",
+                "weights": [{ "kilograms": 4.2, "measured_at": "2024-01-10T00:00:00Z" }],
+                "tags": ["indoor", "senior"],
+                "notes": "Likes boxes",
+            }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["breed"], "Siamese");
+        assert_eq!(body["birthdate"], "2020-05-01T00:00:00Z");
+        assert_eq!(body["sex"], "male");
+        assert_eq!(body["weights"][0]["kilograms"], 4.2);
+        assert_eq!(body["tags"], serde_json::json!(["indoor", "senior"]));
+
+        for invalid in [
+            serde_json::json!({ "name": "" }),
+            serde_json::json!({ "name": "Tom", "tags": ["in,door"] }),
+            serde_json::json!({ "name": "Tom", "birthdate": "2999-01-01T00:00:00Z" }),
+            serde_json::json!({ "name": "Tom", "weights": [{ "kilograms": -1.0, "measured_at": "2024-01-10T00:00:00Z" }] }),
+        ] {
+            let res = client
+                .post(app.url("/v1/cats"))
+                .header("Authorization", format!("Bearer {}", token))
+                .json(&invalid)
+                .send()
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", invalid);
+        }
+
+        let res = client
+            .get(app.url("/v1/cats?tags[in]=senior,kitten&breed[eq]=Siamese"))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<Vec<PublicCat>>().await.unwrap();
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].name, "Tigrin");
+    });
+}
+
 #[test]
 fn get_cats_route() {
-    use_app(async move {
-        let user = create_user("nico@test.com").await.unwrap();
-        let token = create_user_token(user.clone()).await.unwrap();
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
 
         let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
-        Cat::create(tigrin).await.unwrap();
+        Cat::create(app.db(), tigrin).await.unwrap();
 
         let cielito = Cat::new(user.id.unwrap(), "Cielito".to_owned());
-        Cat::create(cielito).await.unwrap();
+        Cat::create(app.db(), cielito).await.unwrap();
 
         let client = reqwest::Client::new();
         let res = client
-            .get("http://localhost:8088/v1/cats")
+            .get(app.url("/v1/cats"))
             .header("Authorization", format!("Bearer {}", token))
             .send()
             .await
@@ -82,6 +151,11 @@ fn get_cats_route() {
         assert_eq!(headers.get("X-Pagination-Count").unwrap(), "2");
         assert_eq!(headers.get("X-Pagination-Offset").unwrap(), "0");
         assert_eq!(headers.get("X-Pagination-Limit").unwrap(), "100");
+        assert_eq!(headers.get("X-Pagination-Pages").unwrap(), "1");
+        assert_eq!(
+            headers.get("Link").unwrap(),
+            r#"</v1/cats?offset=0>; rel="first", </v1/cats?offset=0>; rel="last""#
+        );
 
         // Body:
         let body = res.json::<Vec<PublicCat>>().await.unwrap();
@@ -99,21 +173,216 @@ fn get_cats_route() {
     });
 }
 
+#[test]
+fn get_cats_route_with_cursor() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+
+        for name in ["Tigrin", "Cielito", "Cholin"] {
+            let cat = Cat::new(user.id.unwrap(), name.to_owned());
+            Cat::create(app.db(), cat).await.unwrap();
+        }
+
+        let client = reqwest::Client::new();
+        let res = client
+            .get(app.url("/v1/cats?limit=2"))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        let cursor = res
+            .headers()
+            .get("X-Pagination-Next-Cursor")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = res.json::<Vec<PublicCat>>().await.unwrap();
+        let names = body.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Cholin", "Cielito"]);
+
+        // A cat created meanwhile doesn't shift the next page.
+        let cat = Cat::new(user.id.unwrap(), "Nacho".to_owned());
+        Cat::create(app.db(), cat).await.unwrap();
+
+        let res = client
+            .get(app.url(&format!("/v1/cats?limit=2&cursor={}", cursor)))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get("X-Pagination-Next-Cursor").is_none());
+        assert!(res.headers().get("X-Pagination-Offset").is_none());
+        let body = res.json::<Vec<PublicCat>>().await.unwrap();
+        let names = body.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Tigrin"]);
+
+        let res = client
+            .get(app.url("/v1/cats?cursor=not-a-cursor"))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    });
+}
+
+#[test]
+fn get_cats_route_with_invalid_pagination() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user).await.unwrap();
+
+        let client = reqwest::Client::new();
+        for query in ["limit=4000000000", "limit=0", "limit=ten", "offset=-1"] {
+            let res = client
+                .get(app.url(&format!("/v1/cats?{}", query)))
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", query);
+
+            let body = res.json::<serde_json::Value>().await.unwrap();
+            assert_eq!(body["code"], 40007, "{}", query);
+        }
+    });
+}
+
+#[test]
+fn get_cats_route_with_links_and_envelope() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+
+        for name in ["Tigrin", "Cielito", "Cholin", "Nacho", "Michi"] {
+            let cat = Cat::new(user.id.unwrap(), name.to_owned());
+            Cat::create(app.db(), cat).await.unwrap();
+        }
+
+        let client = reqwest::Client::new();
+        let res = client
+            .get(app.url("/v1/cats?limit=2&offset=2&envelope=true"))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        // Response headers:
+        let headers = res.headers();
+        assert_eq!(headers.get("X-Pagination-Pages").unwrap(), "3");
+        assert_eq!(
+            headers.get("Link").unwrap(),
+            concat!(
+                r#"</v1/cats?limit=2&envelope=true&offset=0>; rel="first", "#,
+                r#"</v1/cats?limit=2&envelope=true&offset=0>; rel="prev", "#,
+                r#"</v1/cats?limit=2&envelope=true&offset=4>; rel="next", "#,
+                r#"</v1/cats?limit=2&envelope=true&offset=4>; rel="last""#,
+            )
+        );
+
+        // Body:
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 2);
+        assert_eq!(body["meta"]["count"], 5);
+        assert_eq!(body["meta"]["pages"], 3);
+        assert!(body["meta"]["next_cursor"].is_string());
+
+        // The envelope can also be requested with an Accept profile:
+        let res = client
+            .get(app.url("/v1/cats"))
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Accept", r#"application/json; profile="envelope""#)
+            .send()
+            .await
+            .unwrap();
+
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["data"].as_array().unwrap().len(), 5);
+    });
+}
+
+#[test]
+fn get_cats_route_with_filters_and_sort() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+
+        for name in ["Tigrin", "Tom", "Cielito", "Tito"] {
+            let cat = Cat::new(user.id.unwrap(), name.to_owned());
+            Cat::create(app.db(), cat).await.unwrap();
+        }
+
+        let client = reqwest::Client::new();
+        let res = client
+            .get(app.url("/v1/cats?name[prefix]=Ti&sort=name"))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<Vec<PublicCat>>().await.unwrap();
+        let names = body.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Tigrin", "Tito"]);
+
+        let res = client
+            .get(app.url("/v1/cats?name[contains]=I&sort=-name"))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        let body = res.json::<Vec<PublicCat>>().await.unwrap();
+        let names = body.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Tito", "Tigrin", "Cielito"]);
+
+        for query in [
+            "sort=password",
+            "user[prefix]=a",
+            "created_at[gte]=yesterday",
+        ] {
+            let res = client
+                .get(app.url(&format!("/v1/cats?{}", query)))
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await
+                .unwrap();
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", query);
+
+            let body = res.json::<serde_json::Value>().await.unwrap();
+            assert_eq!(body["code"], 40008, "{}", query);
+        }
+    });
+}
+
 #[test]
 fn get_cat_by_id_route() {
-    use_app(async move {
-        let user = create_user("nico@test.com").await.unwrap();
-        let token = create_user_token(user.clone()).await.unwrap();
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
 
         let cholin = Cat::new(user.id.unwrap(), "Cholin".to_owned());
-        let cholin = Cat::create(cholin).await.unwrap();
+        let cholin = Cat::create(app.db(), cholin).await.unwrap();
 
         let client = reqwest::Client::new();
         let res = client
-            .get(format!(
-                "http://localhost:8088/v1/cats/{}",
-                cholin.id.unwrap()
-            ))
+            .get(app.url(&format!("/v1/cats/{}", cholin.id.unwrap())))
             .header("Authorization", format!("Bearer {}", token))
             .send()
             .await
@@ -134,19 +403,18 @@ fn get_cat_by_id_route() {
 
 #[test]
 fn remove_cat_by_id_route() {
-    use_app(async move {
-        let user = create_user("nico@test.com").await.unwrap();
-        let token = create_user_token(user.clone()).await.unwrap();
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
 
         let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
-        let tigrin = Cat::create(tigrin).await.unwrap();
+        let tigrin = Cat::create(app.db(), tigrin).await.unwrap();
 
         let client = reqwest::Client::new();
         let res = client
-            .delete(format!(
-                "http://localhost:8088/v1/cats/{}",
-                tigrin.id.unwrap()
-            ))
+            .delete(app.url(&format!("/v1/cats/{}", tigrin.id.unwrap())))
             .header("Authorization", format!("Bearer {}", token))
             .send()
             .await
@@ -159,7 +427,598 @@ fn remove_cat_by_id_route() {
         assert_eq!(actual, expected);
 
         // Cat from the database
-        let cat = Cat::find_by_id(&tigrin.id.unwrap()).await.unwrap();
+        let cat = Cat::find_by_id(app.db(), &tigrin.id.unwrap())
+            .await
+            .unwrap();
         assert!(cat.is_none(), "Cat should be removed from the database");
     });
 }
+
+#[test]
+fn trash_and_restore_cat_routes() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {}", token);
+
+        let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        let tigrin = Cat::create(app.db(), tigrin).await.unwrap();
+        let tom = Cat::new(user.id.unwrap(), "Tom".to_owned());
+        Cat::create(app.db(), tom).await.unwrap();
+
+        let client = reqwest::Client::new();
+        let res = client
+            .get(app.url(&format!("/v1/cats/{}", tigrin.id.unwrap())))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        let stale_etag = res.headers()["etag"].to_str().unwrap().to_owned();
+
+        let res = client
+            .delete(app.url(&format!("/v1/cats/{}", tigrin.id.unwrap())))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = client
+            .get(app.url("/v1/cats"))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        let body = res.json::<Vec<PublicCat>>().await.unwrap();
+        let names = body.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Tom"], "Trashed cats are not listed");
+
+        let res = client
+            .get(app.url("/v1/cats/trash"))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["x-pagination-count"], "1");
+
+        let body = res.json::<Vec<PublicCat>>().await.unwrap();
+        assert_eq!(body[0].name, "Tigrin");
+        let deleted_at = body[0].deleted_at.unwrap().to_chrono();
+        let purge_at = body[0].purge_at.unwrap().to_chrono();
+        assert_eq!((purge_at - deleted_at).num_days(), 30);
+
+        let restore_url = app.url(&format!("/v1/cats/{}/restore", tigrin.id.unwrap()));
+        let res = client
+            .post(&restore_url)
+            .header("Authorization", &authorization)
+            .header("If-Match", &stale_etag)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            res.status(),
+            StatusCode::PRECONDITION_FAILED,
+            "Deleting the cat changed its ETag"
+        );
+
+        let res = client
+            .post(&restore_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_ne!(res.headers()["etag"], stale_etag.as_str());
+
+        let body = res.json::<PublicCat>().await.unwrap();
+        assert_eq!(body.deleted_at, None);
+        assert_eq!(body.purge_at, None);
+
+        let cat = Cat::find_by_id(app.db(), &tigrin.id.unwrap())
+            .await
+            .unwrap();
+        assert!(cat.is_some(), "Restored cats are found again");
+
+        // Only trashed cats can be restored.
+        let res = client
+            .post(&restore_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    });
+}
+
+#[test]
+fn patch_cat_by_id_route() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+
+        let tigrin = Cat {
+            breed: Some("Siamese".to_owned()),
+            notes: Some("Likes boxes".to_owned()),
+            tags: vec!["indoor".to_owned()],
+            ..Cat::new(user.id.unwrap(), "Tigrin".to_owned())
+        };
+        let tigrin = Cat::create(app.db(), tigrin).await.unwrap();
+        let url = app.url(&format!("/v1/cats/{}", tigrin.id.unwrap()));
+
+        let client = reqwest::Client::new();
+        let res = client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/merge-patch+json")
+            .body(r#"{ "name": "Tigre", "notes": null }"#)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<PublicCat>().await.unwrap();
+        assert_eq!(body.name, "Tigre");
+        assert_eq!(
+            body.breed.as_deref(),
+            Some("Siamese"),
+            "Untouched fields stay"
+        );
+        assert_eq!(body.notes, None, "null removes the field");
+        assert!(body.updated_at > tigrin.updated_at);
+
+        let res = client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("Content-Type", "application/json-patch+json")
+            .body(r#"[{ "op": "add", "path": "/tags/-", "value": "senior" }]"#)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<PublicCat>().await.unwrap();
+        assert_eq!(body.tags, vec!["indoor", "senior"]);
+
+        for (content_type, patch, status, code) in [
+            // The result is validated like a PUT body.
+            (
+                "application/merge-patch+json",
+                r#"{ "name": "" }"#,
+                400,
+                40002,
+            ),
+            (
+                "application/merge-patch+json",
+                r#"{ "name": null }"#,
+                400,
+                40009,
+            ),
+            ("application/merge-patch+json", "{", 400, 40009),
+            (
+                "application/json-patch+json",
+                r#"[{ "op": "test", "path": "/name", "value": "Tom" }]"#,
+                400,
+                40009,
+            ),
+            ("text/plain", r#"{ "name": "Tom" }"#, 415, 40010),
+        ] {
+            let res = client
+                .patch(&url)
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Content-Type", content_type)
+                .body(patch)
+                .send()
+                .await
+                .unwrap();
+
+            assert_eq!(res.status().as_u16(), status, "{}", patch);
+            let body = res.json::<serde_json::Value>().await.unwrap();
+            assert_eq!(body["code"], code, "{}", patch);
+        }
+
+        let cat = Cat::find_by_id(app.db(), &tigrin.id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cat.name, "Tigre", "Failed patches don't write");
+    });
+}
+
+#[test]
+fn cat_routes_with_etags() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+
+        let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        let tigrin = Cat::create(app.db(), tigrin).await.unwrap();
+        let url = app.url(&format!("/v1/cats/{}", tigrin.id.unwrap()));
+        let authorization = format!("Bearer {}", token);
+
+        let client = reqwest::Client::new();
+        let res = client
+            .get(&url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()["etag"].to_str().unwrap().to_owned();
+
+        let res = client
+            .get(&url)
+            .header("Authorization", &authorization)
+            .header("If-None-Match", &etag)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()["etag"], etag.as_str());
+
+        let res = client
+            .put(&url)
+            .header("Authorization", &authorization)
+            .header("If-Match", &etag)
+            .json(&serde_json::json!({ "name": "Tigre" }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let new_etag = res.headers()["etag"].to_str().unwrap().to_owned();
+        assert_ne!(new_etag, etag, "Writes change the entity tag");
+
+        // Another client still holding the first tag can't overwrite the change.
+        let res = client
+            .patch(&url)
+            .header("Authorization", &authorization)
+            .header("If-Match", &etag)
+            .json(&serde_json::json!({ "name": "Tom" }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["code"], 40011);
+
+        let res = client
+            .delete(&url)
+            .header("Authorization", &authorization)
+            .header("If-Match", &etag)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+        let res = client
+            .delete(&url)
+            .header("Authorization", &authorization)
+            .header("If-Match", &new_etag)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    });
+}
+
+#[test]
+fn bulk_cats_route() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {}", token);
+
+        let tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        let tigrin = Cat::create(app.db(), tigrin).await.unwrap();
+        let tom = Cat::new(user.id.unwrap(), "Tom".to_owned());
+        let tom = Cat::create(app.db(), tom).await.unwrap();
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(app.url("/v1/cats/bulk"))
+            .header("Authorization", &authorization)
+            .json(&serde_json::json!({
+                "operations": [
+                    { "op": "create", "cat": { "name": "Cielito", "tags": ["indoor"] } },
+                    { "op": "create", "cat": { "name": "" } },
+                    { "op": "update", "id": tigrin.id.unwrap().to_hex(), "cat": { "name": "Tigre" } },
+                    { "op": "delete", "id": tom.id.unwrap().to_hex() },
+                    { "op": "delete", "id": "64b7f3a2c9e77b3f0a1d2e3f" },
+                ]
+            }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        let statuses = body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| result["status"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec![201, 400, 200, 204, 404]);
+        assert!(body["results"][0]["id"].is_string());
+        assert_eq!(body["results"][1]["error"]["code"], 40002);
+
+        let cats = Cat::find(app.db(), bson::doc! { "user": user.id.unwrap() }, None)
+            .await
+            .unwrap();
+        let mut names = cats.iter().map(|cat| cat.name.as_str()).collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, vec!["Cielito", "Tigre"], "Tom is in the trash");
+
+        // A transactional batch with an invalid operation writes nothing.
+        let res = client
+            .post(app.url("/v1/cats/bulk"))
+            .header("Authorization", &authorization)
+            .json(&serde_json::json!({
+                "transactional": true,
+                "operations": [
+                    { "op": "create", "cat": { "name": "Nacho" } },
+                    { "op": "create", "cat": { "name": "Tito", "tags": ["a,b"] } },
+                ]
+            }))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["code"], 40012);
+
+        let count = Cat::count(app.db(), bson::doc! { "name": "Nacho" })
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let res = client
+            .post(app.url("/v1/cats/bulk"))
+            .header("Authorization", &authorization)
+            .json(&serde_json::json!({
+                "transactional": true,
+                "operations": [
+                    { "op": "create", "cat": { "name": "Nacho" } },
+                    { "op": "update", "id": tigrin.id.unwrap().to_hex(), "cat": { "name": "Tigrecito" } },
+                ]
+            }))
+            .send()
+            .await
+            .unwrap();
+
+        if supports_transactions(app.db()).await {
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.json::<serde_json::Value>().await.unwrap();
+            assert_eq!(body["results"][0]["status"], 201);
+            assert_eq!(body["results"][1]["status"], 200);
+
+            let count = Cat::count(
+                app.db(),
+                bson::doc! { "name": { "$in": ["Nacho", "Tigrecito"] } },
+            )
+            .await
+            .unwrap();
+            assert_eq!(count, 2);
+        } else {
+            // Standalone servers reject the transaction.
+            assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+
+        let res = client
+            .post(app.url("/v1/cats/bulk"))
+            .header("Authorization", &authorization)
+            .json(&serde_json::json!({ "operations": [] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    });
+}
+
+#[test]
+fn get_cats_route_with_token_from_another_app() {
+    use_app(|app| async move {
+        let mut settings = test_settings();
+        settings.auth.secret = "another-secret".into();
+        let other = spawn_app(settings).await;
+
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&other.state.keys, user).await.unwrap();
+
+        let client = reqwest::Client::new();
+        let res = client
+            .get(app.url("/v1/cats"))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+
+        // Status code:
+        let status_code = res.status();
+        let actual = status_code;
+        let expected = StatusCode::UNAUTHORIZED;
+        assert_eq!(actual, expected);
+    });
+}
+
+#[test]
+fn export_cats_route() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {}", token);
+
+        let mut tigrin = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        tigrin.tags = vec!["indoor".to_owned(), "orange".to_owned()];
+        Cat::create(app.db(), tigrin).await.unwrap();
+        let tom = Cat::new(user.id.unwrap(), "Tom".to_owned());
+        Cat::create(app.db(), tom).await.unwrap();
+
+        let client = reqwest::Client::new();
+        let res = client
+            .get(app.url("/v1/cats/export?format=ndjson&sort=name"))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/x-ndjson");
+        let body = res.text().await.unwrap();
+        let cats = body
+            .lines()
+            .map(|line| serde_json::from_str::<PublicCat>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(cats.len(), 2);
+        assert_eq!(cats[0].name, "Tigrin");
+        assert_eq!(cats[1].name, "Tom");
+
+        let res = client
+            .get(app.url("/v1/cats/export?format=json&tags[eq]=indoor"))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let cats = res.json::<Vec<PublicCat>>().await.unwrap();
+        assert_eq!(cats.len(), 1);
+        assert_eq!(cats[0].tags, vec!["indoor", "orange"]);
+
+        let res = client
+            .get(app.url("/v1/cats/export?format=csv&sort=name"))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/csv");
+        let body = res.text().await.unwrap();
+        let lines = body.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "id,name,breed,birthdate,sex,weights,tags,notes,created_at,updated_at"
+        );
+        assert!(lines[1].contains(",Tigrin,,,,,\"indoor,orange\",,"));
+
+        let res = client
+            .get(app.url("/v1/cats/export?format=xml"))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    })
+}
+
+#[test]
+fn import_cats_route() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {}", token);
+
+        let csv = "\
+name,breed,birthdate,sex,weights,tags
+Tigrin,Siamese,2020-05-01T00:00:00Z,male,4.2@2024-01-01T00:00:00Z,\"indoor,orange\"
+,,,,,
+Tom,,,tomcat,,
+";
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(app.url("/v1/cats/import?dry_run=true"))
+            .header("Authorization", &authorization)
+            .header("Content-Type", "text/csv")
+            .body(csv)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["dry_run"], true);
+        assert_eq!(body["total"], 3);
+        assert_eq!(body["valid"], 1);
+        assert_eq!(body["imported"], 0);
+        let lines = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["line"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![3, 4]);
+
+        let count = Cat::count(app.db(), bson::doc! {}).await.unwrap();
+        assert_eq!(count, 0, "A dry run writes nothing");
+
+        let res = client
+            .post(app.url("/v1/cats/import"))
+            .header("Authorization", &authorization)
+            .header("Content-Type", "text/csv")
+            .body(csv)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["imported"], 1);
+
+        let cat = Cat::find_one(app.db(), bson::doc! { "name": "Tigrin" }, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cat.user, user.id.unwrap());
+        assert_eq!(cat.breed.as_deref(), Some("Siamese"));
+        assert_eq!(cat.tags, vec!["indoor", "orange"]);
+        assert_eq!(cat.weights.len(), 1);
+
+        let ndjson = "{\"name\":\"Cielito\",\"tags\":[\"indoor\"]}\n\n{\"name\":\"\"}\n";
+        let res = client
+            .post(app.url("/v1/cats/import"))
+            .header("Authorization", &authorization)
+            .header("Content-Type", "application/x-ndjson")
+            .body(ndjson)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["total"], 2);
+        assert_eq!(body["imported"], 1);
+        assert_eq!(body["errors"][0]["line"], 3);
+
+        let res = client
+            .post(app.url("/v1/cats/import"))
+            .header("Authorization", &authorization)
+            .header("Content-Type", "application/json")
+            .body("[]")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    })
+}
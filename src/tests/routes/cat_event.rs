@@ -0,0 +1,203 @@
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::cat::Cat;
+use crate::models::cat_event::{EventKind, PublicCatEvent};
+use crate::routes::cat_event::Summary;
+use crate::tests::setup::use_app;
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::models::ModelExt;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn cat_event_routes() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {}", token);
+
+        let cat = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        let cat = Cat::create(app.db(), cat).await.unwrap();
+        let events_url = app.url(&format!("/v1/cats/{}/events", cat.id.unwrap().to_hex()));
+
+        let client = reqwest::Client::new();
+        let events = [
+            json!({ "type": "weight", "occurred_at": "2024-01-10T00:00:00Z", "payload": { "kilograms": 4 } }),
+            json!({ "type": "weight", "occurred_at": "2024-02-10T00:00:00Z", "payload": { "kilograms": 4.3 } }),
+            json!({ "type": "vet_visit", "occurred_at": "2024-01-20T00:00:00Z", "payload": { "reason": "Vaccines" } }),
+            json!({ "type": "vet_visit", "occurred_at": "2024-02-03T00:00:00Z" }),
+            json!({ "type": "feeding", "occurred_at": "2024-02-04T00:00:00Z", "payload": { "grams": 50 } }),
+        ];
+        for event in &events {
+            let res = client
+                .post(&events_url)
+                .header("Authorization", &authorization)
+                .json(event)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::CREATED);
+        }
+
+        // Weights need kilograms.
+        let res = client
+            .post(&events_url)
+            .header("Authorization", &authorization)
+            .json(&json!({ "type": "weight", "payload": { "grams": 4000 } }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let res = client
+            .get(&events_url)
+            .header("Authorization", &authorization)
+            .query(&[
+                ("occurred_at[gte]", "2024-02-01T00:00:00Z"),
+                ("occurred_at[lt]", "2024-03-01T00:00:00Z"),
+                ("sort", "occurred_at"),
+            ])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let february = res.json::<Vec<PublicCatEvent>>().await.unwrap();
+        let kinds = february.iter().map(|event| event.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![EventKind::VetVisit, EventKind::Feeding, EventKind::Weight]
+        );
+
+        // Latest occurred_at first by default, cursors follow the same order.
+        let mut occurred_at = Vec::new();
+        let mut cursor = None;
+        loop {
+            let mut query = vec![("limit", "2".to_owned())];
+            query.extend(cursor.map(|cursor| ("cursor", cursor)));
+            let res = client
+                .get(&events_url)
+                .header("Authorization", &authorization)
+                .query(&query)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            cursor = res
+                .headers()
+                .get("X-Pagination-Next-Cursor")
+                .map(|cursor| cursor.to_str().unwrap().to_owned());
+            let page = res.json::<Vec<PublicCatEvent>>().await.unwrap();
+            occurred_at.extend(
+                page.iter()
+                    .map(|event| event.occurred_at.try_to_rfc3339_string().unwrap()),
+            );
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(
+            occurred_at,
+            vec![
+                "2024-02-10T00:00:00Z",
+                "2024-02-04T00:00:00Z",
+                "2024-02-03T00:00:00Z",
+                "2024-01-20T00:00:00Z",
+                "2024-01-10T00:00:00Z",
+            ]
+        );
+
+        let res = client
+            .get(format!("{}/summary", events_url))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let summary = res.json::<Summary>().await.unwrap();
+        assert_eq!(summary.totals[&EventKind::Weight], 2);
+        assert_eq!(summary.totals[&EventKind::VetVisit], 2);
+        assert_eq!(summary.totals[&EventKind::Feeding], 1);
+        let latest_weight = summary.latest_weight.unwrap();
+        assert_eq!(latest_weight.kilograms, 4.3);
+        let months = summary
+            .months
+            .iter()
+            .map(|month| (month.month.as_str(), month.counts[&EventKind::VetVisit]))
+            .collect::<Vec<_>>();
+        assert_eq!(months, vec![("2024-01", 1), ("2024-02", 1)]);
+
+        let res = client
+            .get(format!("{}/summary", events_url))
+            .header("Authorization", &authorization)
+            .query(&[("occurred_at[lt]", "2024-02-01T00:00:00Z")])
+            .send()
+            .await
+            .unwrap();
+        let summary = res.json::<Summary>().await.unwrap();
+        assert_eq!(summary.latest_weight.unwrap().kilograms, 4.0);
+        assert_eq!(summary.months.len(), 1);
+
+        let event_url = format!("{}/{}", events_url, february[0].id.to_hex());
+        let res = client
+            .get(&event_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .delete(&event_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = client
+            .get(&event_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    })
+}
+
+#[test]
+fn cat_event_routes_are_scoped_to_the_cat() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let other = create_user(app.db(), "ana@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, other.clone())
+            .await
+            .unwrap();
+
+        let cat = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        let cat = Cat::create(app.db(), cat).await.unwrap();
+        let events_url = app.url(&format!("/v1/cats/{}/events", cat.id.unwrap().to_hex()));
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(&events_url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&json!({ "type": "note", "payload": { "text": "Hi" } }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client
+            .get(format!("{}/summary", events_url))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    })
+}
@@ -0,0 +1,502 @@
+use reqwest::StatusCode;
+use serde_json::json;
+
+use crate::models::cat::PublicCat;
+use crate::models::household::{PublicHousehold, Role};
+use crate::models::invite::PublicInvite;
+use crate::tests::setup::{spawn_app, test_settings, use_app};
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+#[test]
+fn household_invite_routes() {
+    use_app(|app| async move {
+        let owner = create_user(app.db(), "nico@test.com").await.unwrap();
+        let owner_token = create_user_token(&app.state.keys, owner.clone())
+            .await
+            .unwrap();
+        let owner_auth = format!("Bearer {}", owner_token);
+
+        let invitee = create_user(app.db(), "ana@test.com").await.unwrap();
+        let invitee_token = create_user_token(&app.state.keys, invitee.clone())
+            .await
+            .unwrap();
+        let invitee_auth = format!("Bearer {}", invitee_token);
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(app.url("/v1/households"))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "name": "Home" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let household = res.json::<PublicHousehold>().await.unwrap();
+        assert_eq!(household.members.len(), 1);
+        assert_eq!(household.members[0].user, owner.id.unwrap());
+        assert_eq!(household.members[0].role, Role::Owner);
+        let household_url = app.url(&format!("/v1/households/{}", household.id.to_hex()));
+
+        // Not a member yet.
+        let res = client
+            .get(&household_url)
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client
+            .post(format!("{}/invites", household_url))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "email": "ana@test.com", "role": "viewer" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        // A new invite for the same email replaces the previous one.
+        let res = client
+            .post(format!("{}/invites", household_url))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "email": "ana@test.com", "role": "member" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let res = client
+            .get(app.url("/v1/invites"))
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let invites = res.json::<Vec<PublicInvite>>().await.unwrap();
+        assert_eq!(invites.len(), 1);
+        assert_eq!(invites[0].role, Role::Member);
+        let accept_url = app.url(&format!("/v1/invites/{}/accept", invites[0].id.to_hex()));
+
+        // Only the invited user can accept.
+        let res = client
+            .post(&accept_url)
+            .header("Authorization", &owner_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client
+            .post(&accept_url)
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let household = res.json::<PublicHousehold>().await.unwrap();
+        assert_eq!(household.members.len(), 2);
+        assert_eq!(household.members[1].user, invitee.id.unwrap());
+        assert_eq!(household.members[1].role, Role::Member);
+
+        let res = client
+            .post(&accept_url)
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client
+            .post(format!("{}/invites", household_url))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "email": "ana@test.com" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        // Members can't invite.
+        let res = client
+            .post(format!("{}/invites", household_url))
+            .header("Authorization", &invitee_auth)
+            .json(&json!({ "email": "leo@test.com" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = client
+            .get(app.url("/v1/households"))
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        let households = res.json::<Vec<PublicHousehold>>().await.unwrap();
+        assert_eq!(households.len(), 1);
+
+        // The last owner can't leave.
+        let res = client
+            .delete(format!(
+                "{}/members/{}",
+                household_url,
+                owner.id.unwrap().to_hex()
+            ))
+            .header("Authorization", &owner_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let res = client
+            .delete(format!(
+                "{}/members/{}",
+                household_url,
+                invitee.id.unwrap().to_hex()
+            ))
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = client
+            .get(&household_url)
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    });
+}
+
+#[test]
+fn household_cats_are_shared_by_role() {
+    use_app(|app| async move {
+        let owner = create_user(app.db(), "nico@test.com").await.unwrap();
+        let owner_token = create_user_token(&app.state.keys, owner.clone())
+            .await
+            .unwrap();
+        let owner_auth = format!("Bearer {}", owner_token);
+
+        let viewer = create_user(app.db(), "ana@test.com").await.unwrap();
+        let viewer_token = create_user_token(&app.state.keys, viewer.clone())
+            .await
+            .unwrap();
+        let viewer_auth = format!("Bearer {}", viewer_token);
+
+        let stranger = create_user(app.db(), "leo@test.com").await.unwrap();
+        let stranger_token = create_user_token(&app.state.keys, stranger.clone())
+            .await
+            .unwrap();
+        let stranger_auth = format!("Bearer {}", stranger_token);
+
+        let client = reqwest::Client::new();
+        let household = client
+            .post(app.url("/v1/households"))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "name": "Home" }))
+            .send()
+            .await
+            .unwrap()
+            .json::<PublicHousehold>()
+            .await
+            .unwrap();
+        let household_id = household.id.to_hex();
+
+        let invite = client
+            .post(app.url(&format!("/v1/households/{}/invites", household_id)))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "email": "ana@test.com", "role": "viewer" }))
+            .send()
+            .await
+            .unwrap()
+            .json::<PublicInvite>()
+            .await
+            .unwrap();
+        let res = client
+            .post(app.url(&format!("/v1/invites/{}/accept", invite.id.to_hex())))
+            .header("Authorization", &viewer_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Viewers can't add cats to the household.
+        let res = client
+            .post(app.url("/v1/cats"))
+            .header("Authorization", &viewer_auth)
+            .json(&json!({ "name": "Michi", "household": household_id }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = client
+            .post(app.url("/v1/cats"))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "name": "Tigrin", "household": household_id }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let cat = res.json::<PublicCat>().await.unwrap();
+        assert_eq!(cat.household, Some(household.id));
+        let cat_url = app.url(&format!("/v1/cats/{}", cat.id.to_hex()));
+
+        let res = client
+            .get(app.url("/v1/cats"))
+            .header("Authorization", &viewer_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let cats = res.json::<Vec<PublicCat>>().await.unwrap();
+        assert_eq!(cats.len(), 1);
+        assert_eq!(cats[0].id, cat.id);
+
+        let res = client
+            .get(&cat_url)
+            .header("Authorization", &viewer_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .put(&cat_url)
+            .header("Authorization", &viewer_auth)
+            .json(&json!({ "name": "Michi", "household": household_id }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = client
+            .delete(&cat_url)
+            .header("Authorization", &viewer_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = client
+            .get(&cat_url)
+            .header("Authorization", &stranger_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client
+            .get(app.url("/v1/cats"))
+            .header("Authorization", &stranger_auth)
+            .send()
+            .await
+            .unwrap();
+        let cats = res.json::<Vec<PublicCat>>().await.unwrap();
+        assert!(cats.is_empty());
+    });
+}
+
+#[test]
+fn household_cats_follow_membership() {
+    use_app(|app| async move {
+        let owner = create_user(app.db(), "nico@test.com").await.unwrap();
+        let owner_token = create_user_token(&app.state.keys, owner.clone())
+            .await
+            .unwrap();
+        let owner_auth = format!("Bearer {}", owner_token);
+
+        let member = create_user(app.db(), "ana@test.com").await.unwrap();
+        let member_token = create_user_token(&app.state.keys, member.clone())
+            .await
+            .unwrap();
+        let member_auth = format!("Bearer {}", member_token);
+
+        let client = reqwest::Client::new();
+        let household = client
+            .post(app.url("/v1/households"))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "name": "Home" }))
+            .send()
+            .await
+            .unwrap()
+            .json::<PublicHousehold>()
+            .await
+            .unwrap();
+        let household_id = household.id.to_hex();
+        let household_url = app.url(&format!("/v1/households/{}", household_id));
+
+        let invite = client
+            .post(format!("{}/invites", household_url))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "email": "ana@test.com" }))
+            .send()
+            .await
+            .unwrap()
+            .json::<PublicInvite>()
+            .await
+            .unwrap();
+        client
+            .post(app.url(&format!("/v1/invites/{}/accept", invite.id.to_hex())))
+            .header("Authorization", &member_auth)
+            .send()
+            .await
+            .unwrap();
+
+        let cat = client
+            .post(app.url("/v1/cats"))
+            .header("Authorization", &member_auth)
+            .json(&json!({ "name": "Tigrin", "household": household_id }))
+            .send()
+            .await
+            .unwrap()
+            .json::<PublicCat>()
+            .await
+            .unwrap();
+        let cat_url = app.url(&format!("/v1/cats/{}", cat.id.to_hex()));
+
+        // Only owners take cats out of the household, even their creator can't.
+        let res = client
+            .put(&cat_url)
+            .header("Authorization", &member_auth)
+            .json(&json!({ "name": "Tigrin" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = client
+            .delete(format!(
+                "{}/members/{}",
+                household_url,
+                member.id.unwrap().to_hex()
+            ))
+            .header("Authorization", &member_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        // The cat stays in the household, its creator lost access.
+        let res = client
+            .get(&cat_url)
+            .header("Authorization", &member_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client
+            .get(&cat_url)
+            .header("Authorization", &owner_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Evicting the cat gives it back to its creator.
+        let res = client
+            .put(&cat_url)
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "name": "Tigrin", "household": null }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = client
+            .get(&cat_url)
+            .header("Authorization", &owner_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = client
+            .get(&cat_url)
+            .header("Authorization", &member_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    });
+}
+
+#[test]
+fn household_member_limit_and_concurrent_invites() {
+    use_app(|app| async move {
+        let mut settings = test_settings();
+        settings.households.max_members = 1;
+        let other = spawn_app(settings).await;
+        other.state.db_ready.wait().await;
+
+        let owner = create_user(app.db(), "nico@test.com").await.unwrap();
+        let owner_token = create_user_token(&app.state.keys, owner.clone())
+            .await
+            .unwrap();
+        let owner_auth = format!("Bearer {}", owner_token);
+
+        let invitee = create_user(app.db(), "ana@test.com").await.unwrap();
+        let invitee_token = create_user_token(&app.state.keys, invitee.clone())
+            .await
+            .unwrap();
+        let invitee_auth = format!("Bearer {}", invitee_token);
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(other.url("/v1/households"))
+            .header("Authorization", &owner_auth)
+            .json(&json!({ "name": "Home" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let household = res.json::<PublicHousehold>().await.unwrap();
+        let invites_url = other.url(&format!("/v1/households/{}/invites", household.id.to_hex()));
+
+        // Invites for the same email sent at once all succeed, one is kept.
+        let invite = || {
+            client
+                .post(&invites_url)
+                .header("Authorization", &owner_auth)
+                .json(&json!({ "email": "ana@test.com" }))
+                .send()
+        };
+        let (first, second) = tokio::join!(invite(), invite());
+        assert_eq!(first.unwrap().status(), StatusCode::CREATED);
+        assert_eq!(second.unwrap().status(), StatusCode::CREATED);
+
+        let res = client
+            .get(other.url("/v1/invites"))
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        let invites = res.json::<Vec<PublicInvite>>().await.unwrap();
+        assert_eq!(invites.len(), 1);
+        let accept_url = other.url(&format!("/v1/invites/{}/accept", invites[0].id.to_hex()));
+
+        let res = client
+            .post(&accept_url)
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        // The invite can still be accepted once there is room.
+        let res = client
+            .get(other.url("/v1/invites"))
+            .header("Authorization", &invitee_auth)
+            .send()
+            .await
+            .unwrap();
+        let invites = res.json::<Vec<PublicInvite>>().await.unwrap();
+        assert_eq!(invites.len(), 1);
+    });
+}
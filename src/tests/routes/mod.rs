@@ -1,3 +1,6 @@
 mod cat;
+mod cat_event;
+mod household;
+mod photo;
 mod status;
 mod user;
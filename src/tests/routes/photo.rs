@@ -0,0 +1,198 @@
+use image::{ImageFormat, Rgb, RgbImage};
+use reqwest::multipart::{Form, Part};
+use reqwest::StatusCode;
+use std::io::Cursor;
+
+use crate::models::cat::Cat;
+use crate::models::photo::{Photo, PublicPhoto};
+use crate::tests::setup::{spawn_app, test_settings, use_app};
+use crate::tests::utils::create_user;
+use crate::tests::utils::create_user_token;
+use crate::utils::models::ModelExt;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbImage::from_pixel(width, height, Rgb([200, 120, 40]));
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+
+    bytes.into_inner()
+}
+
+fn form(bytes: Vec<u8>) -> Form {
+    // The declared type is ignored, the image type is sniffed.
+    let part = Part::bytes(bytes)
+        .file_name("tigrin.png")
+        .mime_str("application/octet-stream")
+        .unwrap();
+
+    Form::new().part("photo", part)
+}
+
+#[test]
+fn cat_photo_routes() {
+    use_app(|app| async move {
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {}", token);
+
+        let cat = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        let cat = Cat::create(app.db(), cat).await.unwrap();
+        let photos_url = app.url(&format!("/v1/cats/{}/photos", cat.id.unwrap().to_hex()));
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(&photos_url)
+            .header("Authorization", &authorization)
+            .multipart(form(png(640, 480)))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let photo = res.json::<PublicPhoto>().await.unwrap();
+        assert_eq!(photo.cat, cat.id.unwrap());
+        assert_eq!(photo.content_type, "image/png");
+        assert_eq!((photo.width, photo.height), (640, 480));
+
+        let res = client
+            .get(&photos_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let photos = res.json::<Vec<PublicPhoto>>().await.unwrap();
+        assert_eq!(photos.len(), 1);
+
+        let photo_url = format!("{}/{}", photos_url, photo.id.to_hex());
+        let res = client
+            .get(&photo_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "image/png");
+        assert!(res.headers()["cache-control"]
+            .to_str()
+            .unwrap()
+            .contains("immutable"));
+        let etag = res.headers()["etag"].clone();
+        let bytes = res.bytes().await.unwrap();
+        assert_eq!(bytes.len() as i64, photo.size);
+
+        let res = client
+            .get(&photo_url)
+            .header("Authorization", &authorization)
+            .header("If-None-Match", etag)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        let res = client
+            .get(format!("{}/thumbnail", photo_url))
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "image/jpeg");
+        let thumbnail = image::load_from_memory(&res.bytes().await.unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 192));
+
+        let res = client
+            .delete(&photo_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let count = Photo::count(app.db(), bson::doc! {}).await.unwrap();
+        assert_eq!(count, 0);
+
+        let res = client
+            .get(&photo_url)
+            .header("Authorization", &authorization)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    })
+}
+
+#[test]
+fn upload_cat_photo_route_rejects_invalid_images() {
+    use_app(|app| async move {
+        let mut settings = test_settings();
+        settings.photos.max_bytes = 64 * 1024;
+        settings.photos.max_dimension = 1000;
+        let other = spawn_app(settings).await;
+        other.state.db_ready.wait().await;
+
+        let user = create_user(app.db(), "nico@test.com").await.unwrap();
+        let token = create_user_token(&app.state.keys, user.clone())
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {}", token);
+
+        let cat = Cat::new(user.id.unwrap(), "Tigrin".to_owned());
+        let cat = Cat::create(app.db(), cat).await.unwrap();
+        let photos_url = other.url(&format!("/v1/cats/{}/photos", cat.id.unwrap().to_hex()));
+
+        let client = reqwest::Client::new();
+        let upload = |bytes: Vec<u8>| {
+            client
+                .post(&photos_url)
+                .header("Authorization", &authorization)
+                .multipart(form(bytes))
+                .send()
+        };
+
+        let res = upload(b"not an image".to_vec()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let res = upload(png(2000, 10)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["code"], 40014);
+
+        let res = upload(vec![0; 128 * 1024]).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Fields sent before the photo count towards the body limit.
+        let photo = Part::bytes(png(10, 10)).file_name("tigrin.png");
+        let res = client
+            .post(&photos_url)
+            .header("Authorization", &authorization)
+            .multipart(
+                Form::new()
+                    .text("caption", "Tigrin ".repeat(32 * 1024))
+                    .part("photo", photo),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let res = client
+            .post(&photos_url)
+            .header("Authorization", &authorization)
+            .multipart(Form::new().text("caption", "Tigrin"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let count = Photo::count(app.db(), bson::doc! {}).await.unwrap();
+        assert_eq!(count, 0);
+    })
+}
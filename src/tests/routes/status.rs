@@ -11,8 +11,8 @@ use pretty_assertions::assert_eq;
 
 #[test]
 fn get_status_route() {
-    use_app(async {
-        let res = reqwest::get("http://localhost:8088/status").await.unwrap();
+    use_app(|app| async move {
+        let res = reqwest::get(app.url("/status")).await.unwrap();
         let status_code = res.status();
         let body = res.json::<Json>().await.unwrap();
 
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::models::user::PublicUser;
 use crate::routes::user::AuthenticateResponse;
-use crate::tests::setup::use_app;
+use crate::tests::setup::{spawn_app, test_settings, use_app};
 use crate::tests::utils::create_user;
 
 #[cfg(test)]
@@ -25,10 +25,10 @@ fn post_user_route() {
         password: "Password1".to_owned(),
     };
 
-    use_app(async move {
+    use_app(|app| async move {
         let client = reqwest::Client::new();
         let res = client
-            .post("http://localhost:8088/users")
+            .post(app.url("/users"))
             .json(&body)
             .send()
             .await
@@ -60,12 +60,12 @@ fn authenticate_user_route() {
         password: "Password1".to_owned(),
     };
 
-    use_app(async move {
-        create_user("nahuel@gmail.com").await.unwrap();
+    use_app(|app| async move {
+        create_user(app.db(), "nahuel@gmail.com").await.unwrap();
 
         let client = reqwest::Client::new();
         let res = client
-            .post("http://localhost:8088/users/authenticate")
+            .post(app.url("/users/authenticate"))
             .json(&request_body)
             .send()
             .await
@@ -82,3 +82,35 @@ fn authenticate_user_route() {
         assert_eq!(body.user.email, "nahuel@gmail.com");
     });
 }
+
+#[test]
+fn authenticate_user_route_while_database_is_unavailable() {
+    use_app(|_app| async move {
+        let mut settings = test_settings();
+        settings.database.uri = "mongodb://127.0.0.1:1".into();
+        settings.database.server_selection_timeout_ms = Some(100);
+        let app = spawn_app(settings).await;
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(app.url("/users/authenticate"))
+            .json(&serde_json::json!({ "email": "nahuel@gmail.com", "password": "Password1" }))
+            .send()
+            .await
+            .unwrap();
+
+        // Status code:
+        let status_code = res.status();
+        let actual = status_code;
+        let expected = StatusCode::SERVICE_UNAVAILABLE;
+        assert_eq!(actual, expected);
+
+        // Headers:
+        let retry_after = res.headers().get("retry-after").unwrap();
+        assert_eq!(retry_after, "5");
+
+        // Body:
+        let body = res.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(body["code"], 5011);
+    });
+}
@@ -1,48 +1,82 @@
 use bson::doc;
 use once_cell::sync::Lazy;
 use std::net::SocketAddr;
+use std::path::Path;
 use tokio::net::TcpListener;
 use tokio::runtime::Runtime;
-use tokio::sync::OnceCell;
+use wither::mongodb::Database;
 
 use crate::app::create_app;
 use crate::models::cat::Cat;
+use crate::models::cat_event::CatEvent;
+use crate::models::household::Household;
+use crate::models::invite::Invite;
+use crate::models::photo::Photo;
 use crate::models::user::User;
-use crate::settings::SETTINGS;
+use crate::settings::{Settings, DEFAULT_CONFIG_DIR};
+use crate::state::AppState;
 use crate::utils::models::ModelExt;
 
-static API: OnceCell<()> = OnceCell::const_new();
 static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().unwrap());
 
-pub async fn start_api_once() {
-    API.get_or_init(|| async {
-        std::env::set_var("RUN_MODE", "test");
+pub struct TestApp {
+    pub address: SocketAddr,
+    pub state: AppState,
+}
+
+impl TestApp {
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+
+    pub fn db(&self) -> &Database {
+        &self.state.db
+    }
+}
+
+pub fn test_settings() -> Settings {
+    Settings::from_dir(Path::new(DEFAULT_CONFIG_DIR), "test")
+        .expect("Failed to setup test settings")
+}
+
+// Every call starts a new app instance on a random port, so tests can run
+// apps with different settings side by side.
+pub async fn spawn_app(settings: Settings) -> TestApp {
+    let state = AppState::new(settings)
+        .await
+        .expect("Failed to setup app state");
+
+    let app = create_app(state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("error listening on a random port");
+    let address = listener.local_addr().unwrap();
 
-        let app = create_app().await;
-        let port = SETTINGS.server.port;
-        let address = SocketAddr::from(([0, 0, 0, 0], port));
-        let listener = TcpListener::bind(address)
+    tokio::spawn(async move {
+        axum::serve(listener, app)
             .await
-            .expect("error listening on the assigner port");
-        tokio::spawn(async move {
-            axum::serve(listener, app)
-                .await
-                .expect("Failed to start server");
-        });
-    })
-    .await;
+            .expect("Failed to start server");
+    });
+
+    TestApp { address, state }
 }
 
-pub fn use_app<F>(test: F)
+pub fn use_app<F, Fut>(test: F)
 where
-    F: std::future::Future,
+    F: FnOnce(TestApp) -> Fut,
+    Fut: std::future::Future,
 {
     RUNTIME.block_on(async move {
-        start_api_once().await;
+        let app = spawn_app(test_settings()).await;
+        app.state.db_ready.wait().await;
 
-        Cat::delete_many(doc! {}).await.unwrap();
-        User::delete_many(doc! {}).await.unwrap();
+        Cat::delete_many(app.db(), doc! {}).await.unwrap();
+        Photo::delete_many(app.db(), doc! {}).await.unwrap();
+        CatEvent::delete_many(app.db(), doc! {}).await.unwrap();
+        Household::delete_many(app.db(), doc! {}).await.unwrap();
+        Invite::delete_many(app.db(), doc! {}).await.unwrap();
+        User::delete_many(app.db(), doc! {}).await.unwrap();
 
-        test.await;
+        test(app).await;
     })
 }
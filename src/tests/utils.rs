@@ -1,24 +1,43 @@
+use wither::bson::doc;
+use wither::mongodb::Database;
+
 use crate::errors::Error;
 use crate::models::user::hash_password;
 use crate::models::user::User;
-use crate::settings::SETTINGS;
 use crate::utils::models::ModelExt;
 use crate::utils::token;
+use crate::utils::token::Keys;
 
-pub async fn create_user<T: AsRef<str>>(email: T) -> Result<User, Error> {
+pub async fn create_user<T: AsRef<str>>(db: &Database, email: T) -> Result<User, Error> {
     let name = "Nahuel";
     let password = "Password1";
 
     let password_hash = hash_password(password).await?;
     let user = User::new(name, email.as_ref(), password_hash);
-    let user = User::create(user).await?;
+    let user = User::create(db, user).await?;
 
     Ok(user)
 }
 
-pub async fn create_user_token(user: User) -> Result<String, Error> {
-    let secret = SETTINGS.auth.secret.as_str();
-    let token = token::create(user, secret).unwrap();
+pub async fn create_user_token(keys: &Keys, user: User) -> Result<String, Error> {
+    let token = token::create(user, keys).unwrap();
 
     Ok(token)
 }
+
+/// Transactions need a replica set or a sharded cluster, CI runs a single node
+/// replica set. Tests that need them pass without asserting on a standalone
+/// server.
+pub async fn supports_transactions(db: &Database) -> bool {
+    let hello = db
+        .run_command(doc! { "hello": 1 }, None)
+        .await
+        .expect("Failed to run the hello command");
+
+    let supported = hello.contains_key("setName") || hello.get_str("msg") == Ok("isdbgrid");
+    if !supported {
+        eprintln!("Skipping transaction checks, the server is not a replica set");
+    }
+
+    supported
+}
@@ -1,10 +1,16 @@
+use std::sync::Arc;
+
 use crate::errors::AuthenticateError;
 use crate::errors::Error;
-use crate::settings::SETTINGS;
 use crate::utils::token;
-use crate::utils::token::TokenUser;
+use crate::utils::token::{Keys, TokenUser};
 
-use axum::{async_trait, extract::FromRequestParts, http::request::Parts, RequestPartsExt};
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+    RequestPartsExt,
+};
 
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
@@ -14,19 +20,20 @@ use axum_extra::{
 #[async_trait]
 impl<S> FromRequestParts<S> for TokenUser
 where
+    Arc<Keys>: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = Error;
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let TypedHeader(Authorization(bearer)) = parts
             .extract::<TypedHeader<Authorization<Bearer>>>()
             .await
             .map_err(|_| AuthenticateError::InvalidToken)?;
 
-        let secret = SETTINGS.auth.secret.as_str();
+        let keys = Arc::<Keys>::from_ref(state);
         let token_data =
-            token::decode(bearer.token(), secret).map_err(|_| AuthenticateError::InvalidToken)?;
+            token::decode(bearer.token(), &keys).map_err(|_| AuthenticateError::InvalidToken)?;
 
         Ok(token_data.claims.user)
     }
@@ -0,0 +1,170 @@
+use axum::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::request::Parts;
+use std::sync::Arc;
+use tracing::debug;
+use wither::bson::{doc, oid::ObjectId};
+use wither::mongodb::Database;
+
+use crate::database::{Db, Readiness};
+use crate::errors::Error;
+use crate::models::cat::Cat;
+use crate::models::household::{Household, Role};
+use crate::utils::models::ModelExt;
+use crate::utils::query::Filter;
+use crate::utils::to_object_id::to_object_id;
+use crate::utils::token::{Keys, TokenUser};
+
+// Every cat route authorizes through this extractor instead of comparing
+// owners. A user has full access to the cats they created outside of any
+// household. Once a cat is in a household, only the role the user has in it
+// counts, so members who leave lose access to the cats they added.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Read,
+    Write,
+}
+
+/// The requesting user with their household memberships.
+pub struct CatAccess {
+    pub user: TokenUser,
+    households: Vec<(ObjectId, Role)>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CatAccess
+where
+    Arc<Keys>: FromRef<S>,
+    Database: FromRef<S>,
+    Readiness: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = TokenUser::from_request_parts(parts, state).await?;
+        let Db(db) = Db::from_request_parts(parts, state).await?;
+
+        let households = Household::find(&db, doc! { "members.user": user.id }, None)
+            .await?
+            .into_iter()
+            .filter_map(|household| Some((household.id?, household.role_of(&user.id)?)))
+            .collect();
+
+        Ok(Self { user, households })
+    }
+}
+
+impl CatAccess {
+    /// Matches the cats the user has `permission` on. Wrapped in `$and` so it
+    /// combines with other `$or` conditions, like pagination cursors.
+    pub fn filter(&self, permission: Permission) -> Filter<Cat> {
+        let households = self
+            .households
+            .iter()
+            .filter(|(_, role)| allows(*role, permission))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        Filter::raw(doc! {
+            "$and": [{
+                "$or": [
+                    { "user": self.user.id, "household": null },
+                    { "household": { "$in": households } },
+                ]
+            }]
+        })
+    }
+
+    pub fn role_in(&self, household: &ObjectId) -> Option<Role> {
+        self.households
+            .iter()
+            .find(|(id, _)| id == household)
+            .map(|(_, role)| *role)
+    }
+
+    pub fn allows(&self, cat: &Cat, permission: Permission) -> bool {
+        match cat.household {
+            Some(household) => self
+                .role_in(&household)
+                .is_some_and(|role| allows(role, permission)),
+            None => cat.user == self.user.id,
+        }
+    }
+
+    /// Fails with `403 Forbidden` unless the user has `permission` on the cat.
+    pub fn check(&self, cat: &Cat, permission: Permission) -> Result<(), Error> {
+        if self.allows(cat, permission) {
+            return Ok(());
+        }
+
+        debug!("Cat is read only for the user, returning 403 status code");
+        Err(Error::Forbidden(
+            "your role in the cat's household is read only".to_owned(),
+        ))
+    }
+
+    /// The cat with the given id, `404 Not Found` when the user can't read it
+    /// and `403 Forbidden` when they can read it but asked to write.
+    pub async fn find_cat(
+        &self,
+        db: &Database,
+        id: String,
+        permission: Permission,
+    ) -> Result<Cat, Error> {
+        let cat_id = to_object_id(id)?;
+        let filter = self.filter(Permission::Read).eq(Cat::ID, cat_id);
+
+        let Some(cat) = Cat::find_one(db, filter, None).await? else {
+            debug!("Cat not found, returning 404 status code");
+            return Err(Error::not_found());
+        };
+
+        self.check(&cat, permission)?;
+
+        Ok(cat)
+    }
+
+    /// Checks the user can save `cat`, either a new cat or the next state of
+    /// `current`. Cats can only be put in households the user can write to.
+    /// Owners of a cat's household move it to another household or evict it,
+    /// which gives it back to the user who created it.
+    pub fn check_save(&self, current: Option<&Cat>, cat: &Cat) -> Result<(), Error> {
+        if let Some(current) = current {
+            if current.household == cat.household {
+                return Ok(());
+            }
+
+            let is_owner = current
+                .household
+                .is_some_and(|household| self.role_in(&household) == Some(Role::Owner));
+            if current.household.is_some() && !is_owner {
+                return Err(Error::Forbidden(
+                    "only the household's owners can move its cats".to_owned(),
+                ));
+            }
+        }
+
+        match cat.household {
+            Some(household)
+                if !self
+                    .role_in(&household)
+                    .is_some_and(|role| allows(role, Permission::Write)) =>
+            {
+                Err(Error::Forbidden(format!(
+                    "you can't add cats to household {}",
+                    household
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn allows(role: Role, permission: Permission) -> bool {
+    match permission {
+        Permission::Read => true,
+        Permission::Write => role.can_write(),
+    }
+}
@@ -1,6 +1,6 @@
 use axum::{
     http::header::{self, HeaderValue},
-    http::StatusCode,
+    http::{StatusCode, Uri},
     response::{IntoResponse, IntoResponseParts, Response, ResponseParts},
 };
 use bytes::{BufMut, BytesMut};
@@ -8,6 +8,7 @@ use serde::Serialize;
 use tracing::error;
 
 use crate::errors::Error;
+use crate::utils::pagination::Cursor;
 
 pub type CustomResponseResult<T> = Result<CustomResponse<T>, Error>;
 
@@ -24,11 +25,99 @@ pub struct CustomResponseBuilder<T: Serialize> {
     pub pagination: Option<ResponsePagination>,
 }
 
+/// Built with `Pagination::response`.
 #[derive(Debug)]
 pub struct ResponsePagination {
     pub count: u64,
     pub offset: u64,
     pub limit: u32,
+    /// Cursor the page was requested with, `None` in offset mode.
+    pub cursor: Option<Cursor>,
+    /// Cursor of the next page, `None` on the last page.
+    pub next_cursor: Option<Cursor>,
+    pub uri: Uri,
+    pub envelope: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Meta {
+    count: u64,
+    /// Missing in cursor mode, where the offset is ignored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<u64>,
+    limit: u32,
+    pages: u64,
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Envelope<'a, T: Serialize> {
+    data: &'a T,
+    meta: Meta,
+}
+
+impl ResponsePagination {
+    pub fn pages(&self) -> u64 {
+        self.count.div_ceil(u64::from(self.limit.max(1)))
+    }
+
+    fn meta(&self) -> Meta {
+        Meta {
+            count: self.count,
+            offset: self.cursor.is_none().then_some(self.offset),
+            limit: self.limit,
+            pages: self.pages(),
+            next_cursor: self.next_cursor.map(|cursor| cursor.encode()),
+        }
+    }
+
+    /// RFC 8288 links to the other pages. In cursor mode only the next and
+    /// first pages can be addressed.
+    fn links(&self) -> Vec<(&'static str, String)> {
+        let limit = u64::from(self.limit);
+        let mut links = vec![("first", self.link(None, Some(0)))];
+
+        if self.cursor.is_some() {
+            if let Some(cursor) = self.next_cursor {
+                links.push(("next", self.link(Some(cursor), None)));
+            }
+            return links;
+        }
+
+        if self.offset > 0 {
+            let prev = self.offset.saturating_sub(limit);
+            links.push(("prev", self.link(None, Some(prev))));
+        }
+        if self.offset + limit < self.count {
+            links.push(("next", self.link(None, Some(self.offset + limit))));
+        }
+        if self.pages() > 0 {
+            let last = (self.pages() - 1) * limit;
+            links.push(("last", self.link(None, Some(last))));
+        }
+
+        links
+    }
+
+    // The request URI with its query parameters, replacing the position.
+    fn link(&self, cursor: Option<Cursor>, offset: Option<u64>) -> String {
+        let query = self.uri.query().unwrap_or_default();
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            if key != "offset" && key != "cursor" {
+                serializer.append_pair(&key, &value);
+            }
+        }
+        if let Some(offset) = offset {
+            serializer.append_pair("offset", &offset.to_string());
+        }
+        if let Some(cursor) = cursor {
+            serializer.append_pair("cursor", &cursor.encode());
+        }
+
+        format!("{}?{}", self.uri.path(), serializer.finish())
+    }
 }
 
 impl<T> Default for CustomResponseBuilder<T>
@@ -87,7 +176,17 @@ where
         };
 
         let mut bytes = BytesMut::new().writer();
-        if let Err(err) = serde_json::to_writer(&mut bytes, &body) {
+        let written = match &self.pagination {
+            Some(pagination) if pagination.envelope => {
+                let envelope = Envelope {
+                    data: &body,
+                    meta: pagination.meta(),
+                };
+                serde_json::to_writer(&mut bytes, &envelope)
+            }
+            _ => serde_json::to_writer(&mut bytes, &body),
+        };
+        if let Err(err) = written {
             error!("Error serializing response body as JSON: {:?}", err);
             return (StatusCode::INTERNAL_SERVER_ERROR).into_response();
         }
@@ -112,12 +211,34 @@ impl IntoResponseParts for ResponsePagination {
         res.headers_mut()
             .insert("x-pagination-count", self.count.into());
 
-        res.headers_mut()
-            .insert("x-pagination-offset", self.offset.into());
+        // The offset is ignored in cursor mode.
+        if self.cursor.is_none() {
+            res.headers_mut()
+                .insert("x-pagination-offset", self.offset.into());
+        }
 
         res.headers_mut()
             .insert("x-pagination-limit", self.limit.into());
 
+        res.headers_mut()
+            .insert("x-pagination-pages", self.pages().into());
+
+        if let Some(cursor) = self.next_cursor {
+            // Base64 URL safe characters are always a valid header value.
+            let cursor = HeaderValue::from_str(&cursor.encode()).unwrap();
+            res.headers_mut().insert("x-pagination-next-cursor", cursor);
+        }
+
+        let links = self
+            .links()
+            .into_iter()
+            .map(|(rel, uri)| format!("<{}>; rel=\"{}\"", uri, rel))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let links = HeaderValue::from_str(&links)
+            .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        res.headers_mut().insert(header::LINK, links);
+
         Ok(res)
     }
 }
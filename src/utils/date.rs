@@ -5,3 +5,31 @@ pub type Date = bson::DateTime;
 pub fn now() -> Date {
     Utc::now().into()
 }
+
+/// Serde helpers for an optional date as an RFC 3339 string, the `Option`
+/// counterpart of `bson::serde_helpers::bson_datetime_as_rfc3339_string`.
+pub mod option_rfc3339 {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::Date;
+
+    pub fn serialize<S: Serializer>(date: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => {
+                let date = date
+                    .try_to_rfc3339_string()
+                    .map_err(serde::ser::Error::custom)?;
+                serializer.serialize_some(&date)
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Date>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|date| Date::parse_rfc3339_str(&date).map_err(de::Error::custom))
+            .transpose()
+    }
+}
@@ -0,0 +1,32 @@
+use axum_extra::headers::{ETag, IfMatch, IfNoneMatch};
+
+use crate::errors::Error;
+use crate::utils::date::Date;
+
+// Entity tags are derived from `updated_at`, every write sets it so the tag
+// changes with the stored representation without keeping a separate version.
+
+pub fn from_date(updated_at: Date) -> ETag {
+    format!("\"{:x}\"", updated_at.timestamp_millis())
+        .parse()
+        .expect("Hex digits in quotes are a valid entity tag")
+}
+
+/// Fails with `412 Precondition Failed` when `If-Match` was sent and doesn't
+/// match the current tag.
+pub fn check_if_match(if_match: Option<&IfMatch>, updated_at: Date) -> Result<(), Error> {
+    match if_match {
+        Some(if_match) if !if_match.precondition_passes(&from_date(updated_at)) => {
+            Err(Error::PreconditionFailed)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether the client already has the current representation, in which case
+/// a `GET` answers `304 Not Modified`.
+pub fn is_not_modified(if_none_match: Option<&IfNoneMatch>, updated_at: Date) -> bool {
+    if_none_match
+        .map(|if_none_match| !if_none_match.precondition_passes(&from_date(updated_at)))
+        .unwrap_or(false)
+}
@@ -0,0 +1,69 @@
+use image::{ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+use crate::errors::Error;
+use crate::settings::Photos;
+
+pub const THUMBNAIL_CONTENT_TYPE: &str = "image/jpeg";
+
+const FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::WebP,
+    ImageFormat::Gif,
+];
+
+#[derive(Debug)]
+pub struct Processed {
+    /// Sniffed from the bytes, the type declared by the client is ignored.
+    pub content_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub thumbnail: Vec<u8>,
+}
+
+/// Checks the image and generates its thumbnail. Decoding is CPU bound, call
+/// it from a blocking task.
+pub fn process(bytes: &[u8], settings: &Photos) -> Result<Processed, Error> {
+    let format = image::guess_format(bytes)
+        .ok()
+        .filter(|format| FORMATS.contains(format))
+        .ok_or_else(|| {
+            Error::UnsupportedMediaType("photos must be JPEG, PNG, WebP or GIF images".to_owned())
+        })?;
+
+    let invalid = |err: image::ImageError| Error::InvalidUpload(err.to_string());
+
+    let (width, height) = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(invalid)?;
+    if width > settings.max_dimension || height > settings.max_dimension {
+        return Err(Error::InvalidUpload(format!(
+            "images can't be larger than {max}x{max} pixels, got {width}x{height}",
+            max = settings.max_dimension
+        )));
+    }
+
+    // The dimensions were checked, the limits guard against images whose
+    // header doesn't match their data.
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(settings.max_dimension);
+    limits.max_image_height = Some(settings.max_dimension);
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(invalid)?;
+
+    let size = settings.thumbnail_size;
+    let thumbnail = image::DynamicImage::ImageRgb8(image.thumbnail(size, size).to_rgb8());
+    let mut encoded = Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut encoded, ImageFormat::Jpeg)
+        .map_err(invalid)?;
+
+    Ok(Processed {
+        content_type: format.to_mime_type(),
+        width,
+        height,
+        thumbnail: encoded.into_inner(),
+    })
+}
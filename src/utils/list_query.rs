@@ -0,0 +1,229 @@
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use std::marker::PhantomData;
+use wither::bson::{doc, Bson, DateTime, Document};
+
+use crate::errors::Error;
+
+// Filters and sorts for list endpoints, read from the query string:
+//
+//   name[prefix]=Tig            name starts with "Tig", case sensitive
+//   name[contains]=gri          name contains "gri", case insensitive
+//   tags[eq]=indoor             exact match, on arrays any element matches
+//   tags[in]=indoor,senior      any of the comma separated values
+//   created_at[gte]=2024-01-01T00:00:00Z
+//   updated_at[lt]=...          also gt and lte, RFC 3339 dates
+//   sort=name,-created_at       `-` for descending
+//
+// Each model declares the fields it accepts by implementing `Listable`.
+// Parameters without brackets, other than `sort`, are left to other
+// extractors such as `Pagination`.
+
+pub trait Listable {
+    /// Text fields that accept `prefix` and `contains`.
+    const TEXT_FIELDS: &'static [&'static str];
+    /// Fields that accept `eq` and `in`.
+    const EQUALITY_FIELDS: &'static [&'static str];
+    /// Date fields that accept `gt`, `gte`, `lt` and `lte`.
+    const DATE_FIELDS: &'static [&'static str];
+    /// Fields that can be used in `sort`.
+    const SORT_FIELDS: &'static [&'static str];
+    /// Date field of the default order, newest first, and of the cursors.
+    const DEFAULT_SORT: &'static str;
+}
+
+const DATE_OPERATORS: [&str; 4] = ["gt", "gte", "lt", "lte"];
+const EQUALITY_OPERATORS: [&str; 2] = ["eq", "in"];
+
+#[derive(Debug, Clone)]
+pub struct ListQuery<M> {
+    pub filter: Document,
+    /// `None` when the request doesn't set `sort`.
+    pub sort: Option<Document>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<M: Listable> ListQuery<M> {
+    /// The requested sort, or newest first by `M::DEFAULT_SORT`. Ends with
+    /// `_id` so documents with equal values keep a stable order across pages.
+    pub fn sort_or_default(&self) -> Document {
+        let field = M::DEFAULT_SORT;
+        let mut sort = self.sort.clone().unwrap_or_else(|| doc! { field: -1_i32 });
+
+        if !sort.contains_key("_id") {
+            let direction = sort.values().last().cloned().unwrap_or(Bson::Int32(-1));
+            sort.insert("_id", direction);
+        }
+
+        sort
+    }
+
+    pub fn parse(query: &str) -> Result<Self, Error> {
+        let mut filter = Document::new();
+        let mut sort = None;
+
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            if key == "sort" {
+                sort = Some(parse_sort::<M>(&value)?);
+                continue;
+            }
+
+            let Some((field, operator)) = parse_key(&key) else {
+                continue;
+            };
+
+            let condition = condition::<M>(field, operator, &value)?;
+
+            match filter.get_mut(field) {
+                Some(Bson::Document(conditions)) => conditions.extend(condition),
+                _ => {
+                    filter.insert(field, condition);
+                }
+            }
+        }
+
+        Ok(Self {
+            filter,
+            sort,
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[async_trait]
+impl<S, M> FromRequestParts<S> for ListQuery<M>
+where
+    M: Listable,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Self::parse(parts.uri.query().unwrap_or_default())
+    }
+}
+
+// Splits `field[operator]`.
+fn parse_key(key: &str) -> Option<(&str, &str)> {
+    let (field, rest) = key.split_once('[')?;
+    let operator = rest.strip_suffix(']')?;
+
+    Some((field, operator))
+}
+
+fn parse_sort<M: Listable>(value: &str) -> Result<Document, Error> {
+    let mut sort = Document::new();
+
+    for field in value.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        let (field, direction) = match field.strip_prefix('-') {
+            Some(field) => (field, -1_i32),
+            None => (field.trim_start_matches('+'), 1_i32),
+        };
+
+        if !M::SORT_FIELDS.contains(&field) {
+            return Err(Error::InvalidQuery(format!(
+                "{} can't be sorted by, sort by one of {}",
+                field,
+                M::SORT_FIELDS.join(", ")
+            )));
+        }
+
+        sort.insert(field, direction);
+    }
+
+    // An empty `sort=` is most likely a client bug, it isn't the default order.
+    if sort.is_empty() {
+        return Err(Error::InvalidQuery(format!(
+            "sort must list at least one of {}",
+            M::SORT_FIELDS.join(", ")
+        )));
+    }
+
+    Ok(sort)
+}
+
+fn condition<M: Listable>(field: &str, operator: &str, value: &str) -> Result<Document, Error> {
+    // A field can be listed in several groups, e.g. a text field that also
+    // accepts exact matches, so equality operators are checked first.
+    if EQUALITY_OPERATORS.contains(&operator) && M::EQUALITY_FIELDS.contains(&field) {
+        Ok(equality_condition(operator, value))
+    } else if M::TEXT_FIELDS.contains(&field) {
+        text_condition(field, operator, value)
+    } else if M::DATE_FIELDS.contains(&field) {
+        date_condition(field, operator, value)
+    } else if M::EQUALITY_FIELDS.contains(&field) {
+        Err(Error::InvalidQuery(format!(
+            "{}[{}] is not supported, use one of {}",
+            field,
+            operator,
+            EQUALITY_OPERATORS.join(", ")
+        )))
+    } else {
+        let mut fields = [M::TEXT_FIELDS, M::EQUALITY_FIELDS, M::DATE_FIELDS].concat();
+        fields.sort_unstable();
+        fields.dedup();
+
+        Err(Error::InvalidQuery(format!(
+            "{} can't be filtered, filter on one of {}",
+            field,
+            fields.join(", ")
+        )))
+    }
+}
+
+fn text_condition(field: &str, operator: &str, value: &str) -> Result<Document, Error> {
+    match operator {
+        // Anchored and case sensitive, so an index on the field can be used.
+        "prefix" => Ok(doc! { "$regex": format!("^{}", escape_regex(value)) }),
+        "contains" => Ok(doc! { "$regex": escape_regex(value), "$options": "i" }),
+        _ => Err(Error::InvalidQuery(format!(
+            "{}[{}] is not supported, use prefix or contains",
+            field, operator
+        ))),
+    }
+}
+
+fn equality_condition(operator: &str, value: &str) -> Document {
+    match operator {
+        "in" => {
+            let values = value
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<&str>>();
+
+            doc! { "$in": values }
+        }
+        _ => doc! { "$eq": value },
+    }
+}
+
+fn date_condition(field: &str, operator: &str, value: &str) -> Result<Document, Error> {
+    if !DATE_OPERATORS.contains(&operator) {
+        return Err(Error::InvalidQuery(format!(
+            "{}[{}] is not supported, use one of {}",
+            field,
+            operator,
+            DATE_OPERATORS.join(", ")
+        )));
+    }
+
+    let date = DateTime::parse_rfc3339_str(value).map_err(|_| {
+        Error::InvalidQuery(format!("{}[{}] must be an RFC 3339 date", field, operator))
+    })?;
+
+    Ok(doc! { format!("${}", operator): date })
+}
+
+fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
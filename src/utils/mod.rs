@@ -1,7 +1,13 @@
 pub mod authenticate_request;
+pub mod cat_access;
 pub mod custom_response;
 pub mod date;
+pub mod etag;
+pub mod images;
+pub mod list_query;
 pub mod models;
 pub mod pagination;
+pub mod patch;
+pub mod query;
 pub mod to_object_id;
 pub mod token;
@@ -3,63 +3,89 @@
 use async_trait::async_trait;
 use futures::stream::TryStreamExt;
 use serde::{de::DeserializeOwned, ser::Serialize};
+use std::collections::HashSet;
+use std::mem;
 use validator::Validate;
 use wither::bson::doc;
 use wither::bson::from_bson;
 use wither::bson::Bson;
 use wither::bson::Document;
 use wither::bson::{self, oid::ObjectId};
+use wither::mongodb::error::{Error as MongoError, ErrorKind};
 use wither::mongodb::options::FindOneAndUpdateOptions;
 use wither::mongodb::options::FindOneOptions;
 use wither::mongodb::options::FindOptions;
+use wither::mongodb::options::InsertManyOptions;
 use wither::mongodb::options::ReturnDocument;
 use wither::mongodb::options::UpdateOptions;
 use wither::mongodb::results::DeleteResult;
 use wither::mongodb::results::UpdateResult;
+use wither::mongodb::IndexModel;
+use wither::mongodb::{ClientSession, Database};
 use wither::Model as WitherModel;
 use wither::ModelCursor;
 
-use crate::database;
 use crate::errors::Error;
+use crate::utils::query::{Filter, Update};
+
+// Server error code for a missing database or collection.
+const NAMESPACE_NOT_FOUND: i32 = 26;
 
 // This is the Model trait. All models that have a MongoDB collection should
 // implement this and therefore inherit theses methods.
+//
+// Models with a `SOFT_DELETE_FIELD` are soft deleted: find, update, count and
+// exists skip documents where the field is set, unless the query filters on
+// the field itself, e.g. to list the trash. Deletes and aggregations are not
+// scoped, `delete_*` removes documents for good, trashed or not.
 #[async_trait]
 pub trait ModelExt
 where
-    Self: WitherModel + Validate,
+    Self: WitherModel + Validate + Send + Sync,
 {
-    async fn create(mut model: Self) -> Result<Self, Error> {
-        let connection = database::connection().await;
+    /// Date field set when a document is soft deleted.
+    const SOFT_DELETE_FIELD: Option<&'static str> = None;
+
+    /// Adds the soft delete condition to a query.
+    fn scope(mut query: Document) -> Document {
+        if let Some(field) = Self::SOFT_DELETE_FIELD {
+            if !query.contains_key(field) {
+                query.insert(field, Bson::Null);
+            }
+        }
+
+        query
+    }
+
+    async fn create(db: &Database, mut model: Self) -> Result<Self, Error> {
         model.validate().map_err(|_error| Error::bad_request())?;
-        model.save(connection, None).await.map_err(Error::Wither)?;
+        model.save(db, None).await.map_err(Error::Wither)?;
 
         Ok(model)
     }
 
-    async fn find_by_id(id: &ObjectId) -> Result<Option<Self>, Error> {
-        let connection = database::connection().await;
-        <Self as WitherModel>::find_one(connection, doc! { "_id": id }, None)
+    async fn find_by_id(db: &Database, id: &ObjectId) -> Result<Option<Self>, Error> {
+        <Self as WitherModel>::find_one(db, Self::scope(doc! { "_id": id }), None)
             .await
             .map_err(Error::Wither)
     }
 
-    async fn find_one<O>(query: Document, options: O) -> Result<Option<Self>, Error>
+    async fn find_one<Q, O>(db: &Database, query: Q, options: O) -> Result<Option<Self>, Error>
     where
+        Q: Into<Filter<Self>> + Send,
         O: Into<Option<FindOneOptions>> + Send,
     {
-        let connection = database::connection().await;
-        <Self as WitherModel>::find_one(connection, query, options)
+        <Self as WitherModel>::find_one(db, Self::scope(into_filter::<Self, _>(query)?), options)
             .await
             .map_err(Error::Wither)
     }
 
-    async fn find<O>(query: Document, options: O) -> Result<Vec<Self>, Error>
+    async fn find<Q, O>(db: &Database, query: Q, options: O) -> Result<Vec<Self>, Error>
     where
+        Q: Into<Filter<Self>> + Send,
         O: Into<Option<FindOptions>> + Send,
     {
-        let connection = database::connection().await;
-        <Self as WitherModel>::find(connection, query, options)
+        <Self as WitherModel>::find(db, Self::scope(into_filter::<Self, _>(query)?), options)
             .await
             .map_err(Error::Wither)?
             .try_collect::<Vec<Self>>()
@@ -67,18 +93,22 @@ where
             .map_err(Error::Wither)
     }
 
-    async fn find_and_count<O>(query: Document, options: O) -> Result<(Vec<Self>, u64), Error>
+    async fn find_and_count<Q, O>(
+        db: &Database,
+        query: Q,
+        options: O,
+    ) -> Result<(Vec<Self>, u64), Error>
     where
+        Q: Into<Filter<Self>> + Send,
         O: Into<Option<FindOptions>> + Send,
     {
-        let connection = database::connection().await;
-
-        let count = Self::collection(connection)
+        let query = Self::scope(into_filter::<Self, _>(query)?);
+        let count = Self::collection(db)
             .count_documents(query.clone(), None)
             .await
             .map_err(Error::Mongo)?;
 
-        let items = <Self as WitherModel>::find(connection, query, options.into())
+        let items = <Self as WitherModel>::find(db, query, options.into())
             .await
             .map_err(Error::Wither)?
             .try_collect::<Vec<Self>>()
@@ -88,97 +118,127 @@ where
         Ok((items, count))
     }
 
-    async fn cursor<O>(query: Document, options: O) -> Result<ModelCursor<Self>, Error>
+    async fn cursor<Q, O>(db: &Database, query: Q, options: O) -> Result<ModelCursor<Self>, Error>
     where
+        Q: Into<Filter<Self>> + Send,
         O: Into<Option<FindOptions>> + Send,
     {
-        let connection = database::connection().await;
-        <Self as WitherModel>::find(connection, query, options)
+        <Self as WitherModel>::find(db, Self::scope(into_filter::<Self, _>(query)?), options)
             .await
             .map_err(Error::Wither)
     }
 
-    async fn find_one_and_update(query: Document, update: Document) -> Result<Option<Self>, Error> {
-        let connection = database::connection().await;
+    async fn find_one_and_update<Q, U>(
+        db: &Database,
+        query: Q,
+        update: U,
+    ) -> Result<Option<Self>, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+        U: Into<Update<Self>> + Send,
+    {
         let options = FindOneAndUpdateOptions::builder()
             .return_document(ReturnDocument::After)
             .build();
 
-        <Self as WitherModel>::find_one_and_update(connection, query, update, options)
-            .await
-            .map_err(Error::Wither)
+        <Self as WitherModel>::find_one_and_update(
+            db,
+            Self::scope(into_filter::<Self, _>(query)?),
+            into_update::<Self, _>(update)?,
+            options,
+        )
+        .await
+        .map_err(Error::Wither)
     }
 
-    async fn update_one<O>(
-        query: Document,
-        update: Document,
+    async fn update_one<Q, U, O>(
+        db: &Database,
+        query: Q,
+        update: U,
         options: O,
     ) -> Result<UpdateResult, Error>
     where
+        Q: Into<Filter<Self>> + Send,
+        U: Into<Update<Self>> + Send,
         O: Into<Option<UpdateOptions>> + Send,
     {
-        let connection = database::connection().await;
-        Self::collection(connection)
-            .update_one(query, update, options)
+        Self::collection(db)
+            .update_one(
+                Self::scope(into_filter::<Self, _>(query)?),
+                into_update::<Self, _>(update)?,
+                options,
+            )
             .await
             .map_err(Error::Mongo)
     }
 
-    async fn update_many<O>(
-        query: Document,
-        update: Document,
+    async fn update_many<Q, U, O>(
+        db: &Database,
+        query: Q,
+        update: U,
         options: O,
     ) -> Result<UpdateResult, Error>
     where
+        Q: Into<Filter<Self>> + Send,
+        U: Into<Update<Self>> + Send,
         O: Into<Option<UpdateOptions>> + Send,
     {
-        let connection = database::connection().await;
-        Self::collection(connection)
-            .update_many(query, update, options)
+        Self::collection(db)
+            .update_many(
+                Self::scope(into_filter::<Self, _>(query)?),
+                into_update::<Self, _>(update)?,
+                options,
+            )
             .await
             .map_err(Error::Mongo)
     }
 
-    async fn delete_many(query: Document) -> Result<DeleteResult, Error> {
-        let connection = database::connection().await;
-        <Self as WitherModel>::delete_many(connection, query, None)
+    async fn delete_many<Q>(db: &Database, query: Q) -> Result<DeleteResult, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        <Self as WitherModel>::delete_many(db, into_filter::<Self, _>(query)?, None)
             .await
             .map_err(Error::Wither)
     }
 
-    async fn delete_one(query: Document) -> Result<DeleteResult, Error> {
-        let connection = database::connection().await;
-        Self::collection(connection)
-            .delete_one(query, None)
+    async fn delete_one<Q>(db: &Database, query: Q) -> Result<DeleteResult, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        Self::collection(db)
+            .delete_one(into_filter::<Self, _>(query)?, None)
             .await
             .map_err(Error::Mongo)
     }
 
-    async fn count(query: Document) -> Result<u64, Error> {
-        let connection = database::connection().await;
-        Self::collection(connection)
-            .count_documents(query, None)
+    async fn count<Q>(db: &Database, query: Q) -> Result<u64, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        Self::collection(db)
+            .count_documents(Self::scope(into_filter::<Self, _>(query)?), None)
             .await
             .map_err(Error::Mongo)
     }
 
-    async fn exists(query: Document) -> Result<bool, Error> {
-        let connection = database::connection().await;
-        let count = Self::collection(connection)
-            .count_documents(query, None)
+    async fn exists<Q>(db: &Database, query: Q) -> Result<bool, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        let count = Self::collection(db)
+            .count_documents(Self::scope(into_filter::<Self, _>(query)?), None)
             .await
             .map_err(Error::Mongo)?;
 
         Ok(count > 0)
     }
 
-    async fn aggregate<A>(pipeline: Vec<Document>) -> Result<Vec<A>, Error>
+    async fn aggregate<A>(db: &Database, pipeline: Vec<Document>) -> Result<Vec<A>, Error>
     where
         A: Serialize + DeserializeOwned,
     {
-        let connection = database::connection().await;
-
-        let documents = Self::collection(connection)
+        let documents = Self::collection(db)
             .aggregate(pipeline, None)
             .await
             .map_err(Error::Mongo)?
@@ -195,8 +255,731 @@ where
         Ok(documents)
     }
 
-    async fn sync_indexes() -> Result<(), Error> {
-        let connection = database::connection().await;
-        Self::sync(connection).await.map_err(Error::Wither)
+    // The `_with_session` variants run the operation in the given session, so
+    // several operations can share a transaction, see
+    // `database::with_transaction`.
+
+    async fn create_with_session(
+        db: &Database,
+        mut model: Self,
+        session: &mut ClientSession,
+    ) -> Result<Self, Error> {
+        model.validate().map_err(|_error| Error::bad_request())?;
+
+        let document = model.document_from_instance().map_err(Error::Wither)?;
+        let result = Self::collection(db)
+            .insert_one_with_session(document, None, session)
+            .await
+            .map_err(Error::Mongo)?;
+
+        if let Bson::ObjectId(id) = result.inserted_id {
+            model.set_id(id);
+        }
+
+        Ok(model)
+    }
+
+    async fn find_by_id_with_session(
+        db: &Database,
+        id: &ObjectId,
+        session: &mut ClientSession,
+    ) -> Result<Option<Self>, Error> {
+        Self::find_one_with_session(db, doc! { "_id": id }, None, session).await
+    }
+
+    async fn find_one_with_session<Q, O>(
+        db: &Database,
+        query: Q,
+        options: O,
+        session: &mut ClientSession,
+    ) -> Result<Option<Self>, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+        O: Into<Option<FindOneOptions>> + Send,
+    {
+        Self::collection(db)
+            .find_one_with_session(
+                Self::scope(into_filter::<Self, _>(query)?),
+                options,
+                session,
+            )
+            .await
+            .map_err(Error::Mongo)?
+            .map(Self::instance_from_document)
+            .transpose()
+            .map_err(Error::Wither)
+    }
+
+    async fn find_with_session<Q, O>(
+        db: &Database,
+        query: Q,
+        options: O,
+        session: &mut ClientSession,
+    ) -> Result<Vec<Self>, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+        O: Into<Option<FindOptions>> + Send,
+    {
+        let documents = Self::collection(db)
+            .find_with_session(
+                Self::scope(into_filter::<Self, _>(query)?),
+                options,
+                session,
+            )
+            .await
+            .map_err(Error::Mongo)?
+            .stream(session)
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(Error::Mongo)?;
+
+        documents
+            .into_iter()
+            .map(Self::instance_from_document)
+            .collect::<Result<Vec<Self>, _>>()
+            .map_err(Error::Wither)
+    }
+
+    async fn find_one_and_update_with_session<Q, U>(
+        db: &Database,
+        query: Q,
+        update: U,
+        session: &mut ClientSession,
+    ) -> Result<Option<Self>, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+        U: Into<Update<Self>> + Send,
+    {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        Self::collection(db)
+            .find_one_and_update_with_session(
+                Self::scope(into_filter::<Self, _>(query)?),
+                into_update::<Self, _>(update)?,
+                options,
+                session,
+            )
+            .await
+            .map_err(Error::Mongo)?
+            .map(Self::instance_from_document)
+            .transpose()
+            .map_err(Error::Wither)
+    }
+
+    async fn update_one_with_session<Q, U, O>(
+        db: &Database,
+        query: Q,
+        update: U,
+        options: O,
+        session: &mut ClientSession,
+    ) -> Result<UpdateResult, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+        U: Into<Update<Self>> + Send,
+        O: Into<Option<UpdateOptions>> + Send,
+    {
+        Self::collection(db)
+            .update_one_with_session(
+                Self::scope(into_filter::<Self, _>(query)?),
+                into_update::<Self, _>(update)?,
+                options,
+                session,
+            )
+            .await
+            .map_err(Error::Mongo)
+    }
+
+    async fn update_many_with_session<Q, U, O>(
+        db: &Database,
+        query: Q,
+        update: U,
+        options: O,
+        session: &mut ClientSession,
+    ) -> Result<UpdateResult, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+        U: Into<Update<Self>> + Send,
+        O: Into<Option<UpdateOptions>> + Send,
+    {
+        Self::collection(db)
+            .update_many_with_session(
+                Self::scope(into_filter::<Self, _>(query)?),
+                into_update::<Self, _>(update)?,
+                options,
+                session,
+            )
+            .await
+            .map_err(Error::Mongo)
+    }
+
+    async fn delete_many_with_session<Q>(
+        db: &Database,
+        query: Q,
+        session: &mut ClientSession,
+    ) -> Result<DeleteResult, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        Self::collection(db)
+            .delete_many_with_session(into_filter::<Self, _>(query)?, None, session)
+            .await
+            .map_err(Error::Mongo)
+    }
+
+    async fn delete_one_with_session<Q>(
+        db: &Database,
+        query: Q,
+        session: &mut ClientSession,
+    ) -> Result<DeleteResult, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        Self::collection(db)
+            .delete_one_with_session(into_filter::<Self, _>(query)?, None, session)
+            .await
+            .map_err(Error::Mongo)
+    }
+
+    async fn count_with_session<Q>(
+        db: &Database,
+        query: Q,
+        session: &mut ClientSession,
+    ) -> Result<u64, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        Self::collection(db)
+            .count_documents_with_session(
+                Self::scope(into_filter::<Self, _>(query)?),
+                None,
+                session,
+            )
+            .await
+            .map_err(Error::Mongo)
+    }
+
+    async fn exists_with_session<Q>(
+        db: &Database,
+        query: Q,
+        session: &mut ClientSession,
+    ) -> Result<bool, Error>
+    where
+        Q: Into<Filter<Self>> + Send,
+    {
+        let count = Self::count_with_session(db, query, session).await?;
+
+        Ok(count > 0)
+    }
+
+    /// Validates and inserts the models in one unordered batch. Results are in
+    /// the order of `models`, a model that fails validation or is rejected by
+    /// the server doesn't stop the others.
+    async fn insert_many(
+        db: &Database,
+        models: Vec<Self>,
+    ) -> Result<Vec<Result<Self, Error>>, Error> {
+        insert_batch(db, models, None).await
+    }
+
+    async fn insert_many_with_session(
+        db: &Database,
+        models: Vec<Self>,
+        session: &mut ClientSession,
+    ) -> Result<Vec<Result<Self, Error>>, Error> {
+        insert_batch(db, models, Some(session)).await
+    }
+
+    /// Applies `operations` in order, consecutive operations of the same kind
+    /// are sent as one command. Results are in the order of `operations`, an
+    /// update or delete by `_id` whose filter matches nothing is
+    /// `WriteOutcome::Unmatched`.
+    async fn bulk_write(
+        db: &Database,
+        operations: Vec<WriteModel<Self>>,
+    ) -> Result<Vec<Result<WriteOutcome<Self>, Error>>, Error> {
+        write_batch(db, operations, None).await
+    }
+
+    async fn bulk_write_with_session(
+        db: &Database,
+        operations: Vec<WriteModel<Self>>,
+        session: &mut ClientSession,
+    ) -> Result<Vec<Result<WriteOutcome<Self>, Error>>, Error> {
+        write_batch(db, operations, Some(session)).await
+    }
+
+    /// Creates the indexes declared on the model. Existing indexes are left
+    /// untouched, unlike wither's `sync` which drops undeclared ones.
+    async fn create_indexes(db: &Database, background: bool) -> Result<(), Error> {
+        let indexes = Self::indexes()
+            .into_iter()
+            .map(|mut index| {
+                let mut options = index.options.take().unwrap_or_default();
+                options.background = Some(background);
+                index.options = Some(options);
+                index
+            })
+            .collect::<Vec<IndexModel>>();
+
+        if indexes.is_empty() {
+            return Ok(());
+        }
+
+        Self::collection(db)
+            .create_indexes(indexes, None)
+            .await
+            .map_err(Error::Mongo)?;
+
+        Ok(())
+    }
+
+    /// Compares the declared indexes with the ones in the collection, by keys.
+    async fn index_drift(db: &Database) -> Result<IndexDrift, Error> {
+        let existing = match Self::collection(db).list_indexes(None).await {
+            Ok(indexes) => indexes
+                .try_collect::<Vec<IndexModel>>()
+                .await
+                .map_err(Error::Mongo)?,
+            // Collections are created on the first write, until then there
+            // are no indexes to compare.
+            Err(err) if is_namespace_not_found(&err) => Vec::new(),
+            Err(err) => return Err(Error::Mongo(err)),
+        };
+
+        let declared = Self::indexes()
+            .into_iter()
+            .map(|index| index.keys)
+            .collect::<Vec<Document>>();
+
+        let missing = declared
+            .iter()
+            .filter(|keys| !existing.iter().any(|index| &index.keys == *keys))
+            .cloned()
+            .collect();
+
+        let extra = existing
+            .into_iter()
+            .filter(|index| index.keys != doc! { "_id": 1 })
+            .filter(|index| !declared.contains(&index.keys))
+            .filter_map(|index| index.options.and_then(|options| options.name))
+            .collect();
+
+        Ok(IndexDrift {
+            collection: Self::COLLECTION_NAME,
+            missing,
+            extra,
+        })
+    }
+
+    /// Drops the indexes that are not declared on the model and creates the
+    /// missing ones.
+    async fn sync_indexes(db: &Database, background: bool) -> Result<(), Error> {
+        let drift = Self::index_drift(db).await?;
+        for name in &drift.extra {
+            Self::collection(db)
+                .drop_index(name, None)
+                .await
+                .map_err(Error::Mongo)?;
+        }
+
+        Self::create_indexes(db, background).await
+    }
+}
+
+/// An operation of `ModelExt::bulk_write`. Update filters are scoped like
+/// `update_one`, deletes are not, see `ModelExt`.
+#[derive(Debug, Clone)]
+pub enum WriteModel<M> {
+    InsertOne(M),
+    UpdateOne {
+        filter: Filter<M>,
+        update: Update<M>,
+    },
+    DeleteOne {
+        filter: Filter<M>,
+    },
+}
+
+#[derive(Debug)]
+pub enum WriteOutcome<M> {
+    /// The model with its new id.
+    Inserted(M),
+    Updated,
+    Deleted,
+    /// The filter of the update or delete matched no document.
+    Unmatched,
+}
+
+async fn insert_batch<M: ModelExt>(
+    db: &Database,
+    models: Vec<M>,
+    session: Option<&mut ClientSession>,
+) -> Result<Vec<Result<M, Error>>, Error> {
+    let mut results = Vec::with_capacity(models.len());
+    let mut documents = Vec::new();
+    // Position in `results` of every document sent to the server.
+    let mut positions = Vec::new();
+
+    for model in models {
+        if model.validate().is_err() {
+            results.push(Err(Error::bad_request()));
+            continue;
+        }
+
+        documents.push(model.document_from_instance().map_err(Error::Wither)?);
+        positions.push(results.len());
+        results.push(Ok(model));
+    }
+
+    if documents.is_empty() {
+        return Ok(results);
+    }
+
+    let options = InsertManyOptions::builder().ordered(false).build();
+    let collection = M::collection(db);
+    let inserted = match session {
+        Some(session) => {
+            collection
+                .insert_many_with_session(documents, options, session)
+                .await
+        }
+        None => collection.insert_many(documents, options).await,
+    };
+
+    let (inserted_ids, write_errors) = match inserted {
+        Ok(result) => (result.inserted_ids, Vec::new()),
+        Err(err) => match err.kind.as_ref() {
+            ErrorKind::BulkWrite(failure) if failure.write_concern_error.is_none() => (
+                failure.inserted_ids.clone(),
+                failure.write_errors.clone().unwrap_or_default(),
+            ),
+            _ => return Err(Error::Mongo(err)),
+        },
+    };
+
+    for (index, id) in inserted_ids {
+        if let (Bson::ObjectId(id), Ok(model)) = (id, &mut results[positions[index]]) {
+            model.set_id(id);
+        }
+    }
+    for error in write_errors {
+        results[positions[error.index]] = Err(Error::Write(error.message));
+    }
+
+    Ok(results)
+}
+
+// Operations are sent in runs of consecutive operations of the same kind, one
+// command per run, so the batch applies in request order. A run also ends when
+// an operation targets a document already written in it, the statements of a
+// command may run in any order.
+async fn write_batch<M: ModelExt>(
+    db: &Database,
+    operations: Vec<WriteModel<M>>,
+    mut session: Option<&mut ClientSession>,
+) -> Result<Vec<Result<WriteOutcome<M>, Error>>, Error> {
+    let mut results = operations.iter().map(|_| None).collect::<Vec<_>>();
+    let mut run = Vec::new();
+    let mut ids = HashSet::new();
+
+    for (position, operation) in operations.into_iter().enumerate() {
+        let statement = match Statement::from_operation(operation) {
+            Ok(statement) => statement,
+            Err(err) => {
+                results[position] = Some(Err(err));
+                continue;
+            }
+        };
+
+        let id = statement.id();
+        let same_kind = run.last().is_some_and(|(_, last): &(usize, Statement<M>)| {
+            mem::discriminant(last) == mem::discriminant(&statement)
+        });
+        if !same_kind || id.is_some_and(|id| ids.contains(&id)) {
+            write_run(
+                db,
+                mem::take(&mut run),
+                &mut results,
+                session.as_deref_mut(),
+            )
+            .await?;
+            ids.clear();
+        }
+
+        ids.extend(id);
+        run.push((position, statement));
+    }
+    write_run(db, run, &mut results, session).await?;
+
+    Ok(results
+        .into_iter()
+        .map(|result| result.expect("Every operation is in a run"))
+        .collect())
+}
+
+enum Statement<M> {
+    Insert(M),
+    Update { filter: Document, update: Document },
+    Delete { filter: Document },
+}
+
+impl<M: ModelExt> Statement<M> {
+    fn from_operation(operation: WriteModel<M>) -> Result<Self, Error> {
+        Ok(match operation {
+            WriteModel::InsertOne(model) => Self::Insert(model),
+            WriteModel::UpdateOne { filter, update } => Self::Update {
+                filter: M::scope(into_filter::<M, _>(filter)?),
+                update: into_update::<M, _>(update)?,
+            },
+            WriteModel::DeleteOne { filter } => Self::Delete {
+                filter: into_filter::<M, _>(filter)?,
+            },
+        })
+    }
+
+    fn id(&self) -> Option<ObjectId> {
+        match self {
+            Self::Insert(model) => model.id(),
+            Self::Update { filter, .. } | Self::Delete { filter } => {
+                filter.get_object_id("_id").ok()
+            }
+        }
+    }
+}
+
+async fn write_run<M: ModelExt>(
+    db: &Database,
+    run: Vec<(usize, Statement<M>)>,
+    results: &mut [Option<Result<WriteOutcome<M>, Error>>],
+    mut session: Option<&mut ClientSession>,
+) -> Result<(), Error> {
+    // A run holds a single kind of statement, only one of these is filled.
+    let (mut inserts, mut updates, mut deletes) = (Vec::new(), Vec::new(), Vec::new());
+    for (position, statement) in run {
+        match statement {
+            Statement::Insert(model) => inserts.push((position, model)),
+            Statement::Update { filter, update } => updates.push((position, filter, update)),
+            Statement::Delete { filter } => deletes.push((position, filter)),
+        }
+    }
+
+    if !inserts.is_empty() {
+        let (positions, models): (Vec<usize>, Vec<M>) = inserts.into_iter().unzip();
+        let inserted = insert_batch(db, models, session.as_deref_mut()).await?;
+        for (position, result) in positions.into_iter().zip(inserted) {
+            results[position] = Some(result.map(WriteOutcome::Inserted));
+        }
+    }
+
+    if !updates.is_empty() {
+        let statements = updates
+            .iter()
+            .map(|(_, filter, update)| doc! { "q": filter.clone(), "u": update.clone() })
+            .collect();
+        let (matched, errors) =
+            run_statements::<M>(db, "update", "updates", statements, session.as_deref_mut())
+                .await?;
+
+        // The server only counts the matched statements. When some missed,
+        // the documents that don't hold the values their statement sets are
+        // the ones that weren't updated.
+        let applied = updates
+            .iter()
+            .zip(&errors)
+            .filter(|(_, error)| error.is_none())
+            .map(|((_, filter, update), _)| {
+                let mut condition = update.get_document("$set").cloned().unwrap_or_default();
+                condition.insert("_id", filter.get("_id").cloned().unwrap_or(Bson::Null));
+                condition
+            })
+            .collect::<Vec<_>>();
+        let updated = if matched < applied.len() {
+            Some(find_ids::<M>(db, doc! { "$or": applied }, session.as_deref_mut()).await?)
+        } else {
+            None
+        };
+
+        for ((position, filter, _), error) in updates.into_iter().zip(errors) {
+            let missed = updated.as_ref().is_some_and(|updated| {
+                filter
+                    .get_object_id("_id")
+                    .is_ok_and(|id| !updated.contains(&id))
+            });
+            results[position] = Some(match error {
+                Some(error) => Err(error),
+                None if missed => Ok(WriteOutcome::Unmatched),
+                None => Ok(WriteOutcome::Updated),
+            });
+        }
+    }
+
+    if !deletes.is_empty() {
+        let statements = deletes
+            .iter()
+            .map(|(_, filter)| doc! { "q": filter.clone(), "limit": 1 })
+            .collect();
+        let (deleted, errors) =
+            run_statements::<M>(db, "delete", "deletes", statements, session.as_deref_mut())
+                .await?;
+
+        // As with updates, the documents still there weren't deleted.
+        let targeted = deletes
+            .iter()
+            .filter_map(|(_, filter)| filter.get_object_id("_id").ok())
+            .collect::<Vec<_>>();
+        let sent = errors.iter().filter(|error| error.is_none()).count();
+        let remaining = if deleted < sent {
+            Some(find_ids::<M>(db, doc! { "_id": { "$in": targeted } }, session).await?)
+        } else {
+            None
+        };
+
+        for ((position, filter), error) in deletes.into_iter().zip(errors) {
+            let missed = remaining.as_ref().is_some_and(|remaining| {
+                filter
+                    .get_object_id("_id")
+                    .is_ok_and(|id| remaining.contains(&id))
+            });
+            results[position] = Some(match error {
+                Some(error) => Err(error),
+                None if missed => Ok(WriteOutcome::Unmatched),
+                None => Ok(WriteOutcome::Deleted),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Ids of the documents matching `filter`, soft deleted or not.
+async fn find_ids<M: ModelExt>(
+    db: &Database,
+    filter: Document,
+    session: Option<&mut ClientSession>,
+) -> Result<HashSet<ObjectId>, Error> {
+    let options = FindOptions::builder().projection(doc! { "_id": 1 }).build();
+    let collection = M::collection(db);
+    let documents = match session {
+        Some(session) => {
+            collection
+                .find_with_session(filter, options, session)
+                .await?
+                .stream(session)
+                .try_collect::<Vec<Document>>()
+                .await?
+        }
+        None => {
+            collection
+                .find(filter, options)
+                .await?
+                .try_collect::<Vec<Document>>()
+                .await?
+        }
+    };
+
+    Ok(documents
+        .iter()
+        .filter_map(|document| document.get_object_id("_id").ok())
+        .collect())
+}
+
+// Runs an `update` or `delete` command with several statements, the collection
+// API of the driver sends one command per operation. Returns how many
+// documents the statements matched, or deleted, and the error of each
+// statement, if any.
+async fn run_statements<M: ModelExt>(
+    db: &Database,
+    command: &str,
+    field: &str,
+    statements: Vec<Document>,
+    session: Option<&mut ClientSession>,
+) -> Result<(usize, Vec<Option<Error>>), Error> {
+    let mut errors = statements.iter().map(|_| None).collect::<Vec<_>>();
+    if statements.is_empty() {
+        return Ok((0, errors));
+    }
+
+    let mut command = doc! { command: M::COLLECTION_NAME, field: statements, "ordered": false };
+    let response = match session {
+        // Operations in a transaction inherit its write concern.
+        Some(session) => db.run_command_with_session(command, None, session).await?,
+        None => {
+            if let Some(write_concern) = M::collection(db).write_concern() {
+                let write_concern =
+                    bson::to_bson(write_concern).expect("Write concerns serialize to BSON");
+                command.insert("writeConcern", write_concern);
+            }
+            db.run_command(command, None).await?
+        }
+    };
+
+    if let Ok(write_errors) = response.get_array("writeErrors") {
+        for error in write_errors.iter().filter_map(Bson::as_document) {
+            let index = error.get_i32("index").unwrap_or_default() as usize;
+            let message = error.get_str("errmsg").unwrap_or("Write failed");
+            if let Some(slot) = errors.get_mut(index) {
+                *slot = Some(Error::Write(message.to_owned()));
+            }
+        }
+    }
+
+    // Counts come back as int32 or int64 depending on the server.
+    let count = match response.get("n") {
+        Some(Bson::Int32(n)) => *n as usize,
+        Some(Bson::Int64(n)) => *n as usize,
+        _ => 0,
+    };
+
+    Ok((count, errors))
+}
+
+// Unwraps the typed filters and updates `ModelExt` takes, failing when one of
+// their values didn't serialize.
+fn into_filter<M, Q: Into<Filter<M>>>(query: Q) -> Result<Document, Error> {
+    let filter: Filter<M> = query.into();
+    filter.try_into()
+}
+
+fn into_update<M, U: Into<Update<M>>>(update: U) -> Result<Document, Error> {
+    let update: Update<M> = update.into();
+    update.try_into()
+}
+
+fn is_namespace_not_found(err: &MongoError) -> bool {
+    matches!(err.kind.as_ref(), ErrorKind::Command(error) if error.code == NAMESPACE_NOT_FOUND)
+}
+
+#[derive(Debug)]
+pub struct IndexDrift {
+    pub collection: &'static str,
+    /// Keys of the declared indexes missing from the collection.
+    pub missing: Vec<Document>,
+    /// Names of the indexes in the collection that are not declared.
+    pub extra: Vec<String>,
+}
+
+impl IndexDrift {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+impl std::fmt::Display for IndexDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let missing = self
+            .missing
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        write!(
+            f,
+            "{}: missing [{}], not declared [{}]",
+            self.collection,
+            missing.join(", "),
+            self.extra.join(", ")
+        )
     }
 }
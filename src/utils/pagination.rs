@@ -2,50 +2,222 @@
 // From: https://gist.github.com/gillchristian/db76e712cc02bff620b86f0cd2bfb691
 
 use async_trait::async_trait;
-use axum::extract::{FromRequestParts, Query};
-use axum::http::{request::Parts, StatusCode};
+use axum::extract::{FromRef, FromRequestParts, OriginalUri, Query};
+use axum::http::{header, request::Parts, Uri};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use serde::Deserialize;
+use std::sync::Arc;
+use wither::bson::{doc, oid::ObjectId, Document};
 
-#[derive(Debug, Clone, Deserialize)]
-struct Limit {
-    limit: u32,
+use crate::errors::Error;
+use crate::settings::Settings;
+use crate::utils::custom_response::ResponsePagination;
+use crate::utils::date::Date;
+use crate::utils::list_query::Listable;
+
+// Read as strings so invalid values are reported with the parameter name
+// instead of being replaced by the defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Params {
+    limit: Option<String>,
+    offset: Option<String>,
+    cursor: Option<String>,
+    envelope: Option<String>,
 }
 
-impl Default for Limit {
-    fn default() -> Self {
-        Self { limit: 100 }
-    }
+// `Accept: application/json; profile="envelope"` asks for the envelope too,
+// for clients that can't set query parameters.
+const ENVELOPE_PROFILE: &str = "envelope";
+
+/// Position after the last document of a page, sorted by the list's
+/// `Listable::DEFAULT_SORT` field and `_id` descending. Clients get it as an
+/// opaque string and send it back to fetch the next page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cursor {
+    pub date: Date,
+    pub id: ObjectId,
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
-struct Offset {
-    offset: u64,
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let raw = format!("{}:{}", self.date.timestamp_millis(), self.id.to_hex());
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(encoded: &str) -> Option<Self> {
+        let raw = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+        let raw = String::from_utf8(raw).ok()?;
+        let (millis, id) = raw.split_once(':')?;
+
+        Some(Self {
+            date: Date::from_millis(millis.parse().ok()?),
+            id: ObjectId::parse_str(id).ok()?,
+        })
+    }
+
+    /// Matches the documents that come after the cursor, in the order of
+    /// `field`.
+    fn filter(&self, field: &str) -> Document {
+        doc! {
+            "$or": [
+                { field: { "$lt": self.date } },
+                { field: self.date, "_id": { "$lt": self.id } },
+            ]
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Pagination {
-    /// The number of documents to skip before counting.
+    /// The number of documents to skip before counting. Ignored when a cursor
+    /// is given.
     pub offset: u64,
     /// The maximum number of documents to query.
     pub limit: u32,
+    /// Continue after this position instead of skipping `offset` documents.
+    /// Stable under concurrent inserts and doesn't slow down on deep pages.
+    pub cursor: Option<Cursor>,
+    /// The request URI, the `Link` header points to pages of it.
+    pub uri: Uri,
+    /// Wrap the body in `{ data, meta }` instead of only sending headers.
+    pub envelope: bool,
+}
+
+impl Pagination {
+    /// Documents to skip, `None` in cursor mode.
+    pub fn skip(&self) -> Option<u64> {
+        match self.cursor {
+            Some(_) => None,
+            None => Some(self.offset),
+        }
+    }
+
+    /// Documents to fetch, one more than the limit to find out whether there
+    /// is a next page.
+    pub fn fetch_limit(&self) -> i64 {
+        i64::from(self.limit) + 1
+    }
+
+    /// Conditions to merge into the query filter of a list of `M`, empty in
+    /// offset mode.
+    pub fn filter<M: Listable>(&self) -> Document {
+        self.cursor
+            .map(|cursor| cursor.filter(M::DEFAULT_SORT))
+            .unwrap_or_default()
+    }
+
+    /// Drops the extra document fetched with `fetch_limit` and returns the
+    /// cursor of the next page, if there is one.
+    pub fn next_cursor<T, F>(&self, items: &mut Vec<T>, cursor: F) -> Option<Cursor>
+    where
+        F: Fn(&T) -> Cursor,
+    {
+        if items.len() <= self.limit as usize {
+            return None;
+        }
+
+        items.truncate(self.limit as usize);
+        items.last().map(cursor)
+    }
+
+    pub fn response(&self, count: u64, next_cursor: Option<Cursor>) -> ResponsePagination {
+        ResponsePagination {
+            count,
+            offset: self.offset,
+            limit: self.limit,
+            cursor: self.cursor,
+            next_cursor,
+            uri: self.uri.clone(),
+            envelope: self.envelope,
+        }
+    }
 }
 
 #[async_trait]
 impl<S> FromRequestParts<S> for Pagination
 where
+    Arc<Settings>: FromRef<S>,
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, &'static str);
+    type Rejection = Error;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let Query(Limit { limit }) = Query::<Limit>::from_request_parts(parts, state)
-            .await
-            .unwrap_or_default();
+        let settings = Arc::<Settings>::from_ref(state);
+        let bounds = &settings.pagination;
 
-        let Query(Offset { offset }) = Query::<Offset>::from_request_parts(parts, state)
+        let Query(params) = Query::<Params>::from_request_parts(parts, state)
             .await
-            .unwrap_or_default();
+            .map_err(|err| Error::InvalidPagination(err.body_text()))?;
 
-        Ok(Self { limit, offset })
+        let limit = match params.limit {
+            Some(limit) => limit
+                .parse::<u32>()
+                .ok()
+                .filter(|limit| (1..=bounds.max_limit).contains(limit))
+                .ok_or_else(|| {
+                    Error::InvalidPagination(format!(
+                        "limit must be an integer between 1 and {}",
+                        bounds.max_limit
+                    ))
+                })?,
+            None => bounds.default_limit,
+        };
+
+        // MongoDB takes the offset as a signed 64 bit integer.
+        let offset = match params.offset {
+            Some(offset) => offset
+                .parse::<u64>()
+                .ok()
+                .filter(|offset| *offset <= i64::MAX as u64)
+                .ok_or_else(|| {
+                    Error::InvalidPagination("offset must be a non negative integer".to_owned())
+                })?,
+            None => 0,
+        };
+
+        let cursor = match params.cursor {
+            Some(cursor) => Some(Cursor::decode(&cursor).ok_or_else(|| {
+                Error::InvalidPagination("cursor is not a valid cursor".to_owned())
+            })?),
+            None => None,
+        };
+
+        let envelope = match params.envelope.as_deref() {
+            Some("true" | "1") => true,
+            Some("false" | "0") => false,
+            Some(_) => {
+                return Err(Error::InvalidPagination(
+                    "envelope must be true or false".to_owned(),
+                ))
+            }
+            None => accepts_envelope(parts),
+        };
+
+        // Nested routers see the URI without their prefix.
+        let uri = parts
+            .extensions
+            .get::<OriginalUri>()
+            .map(|OriginalUri(uri)| uri.clone())
+            .unwrap_or_else(|| parts.uri.clone());
+
+        Ok(Self {
+            limit,
+            offset,
+            cursor,
+            uri,
+            envelope,
+        })
     }
 }
+
+fn accepts_envelope(parts: &Parts) -> bool {
+    parts
+        .headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|param| param.trim().strip_prefix("profile="))
+        .any(|profile| profile.trim_matches('"') == ENVELOPE_PROFILE)
+}
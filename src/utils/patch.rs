@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use axum::extract::{FromRequest, Request};
+use axum::http::header;
+use bytes::Bytes;
+use serde_json::Value;
+
+use crate::errors::Error;
+
+const MERGE_PATCH: &str = "application/merge-patch+json";
+const JSON_PATCH: &str = "application/json-patch+json";
+
+/// Body of a `PATCH` request, picked by its `Content-Type`:
+///
+///   application/merge-patch+json   JSON Merge Patch (RFC 7396), `null` removes
+///   application/json               same as above
+///   application/json-patch+json    JSON Patch (RFC 6902), a list of operations
+///
+/// Patches apply to the JSON representation of a resource, handlers then read
+/// the result back into their input type and validate it.
+#[derive(Debug, Clone)]
+pub enum Patch {
+    Merge(Value),
+    Json(json_patch::Patch),
+}
+
+impl Patch {
+    pub fn apply(&self, target: &mut Value) -> Result<(), Error> {
+        match self {
+            Patch::Merge(patch) => {
+                json_patch::merge(target, patch);
+                Ok(())
+            }
+            // Operations are applied atomically, the target is left untouched
+            // when one of them fails.
+            Patch::Json(patch) => json_patch::patch(target, &patch.0)
+                .map_err(|error| Error::InvalidPatch(error.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequest<S> for Patch
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .map(|mime| mime.essence_str().to_ascii_lowercase());
+
+        let content_type = match content_type.as_deref() {
+            Some(MERGE_PATCH) | Some("application/json") => MERGE_PATCH,
+            Some(JSON_PATCH) => JSON_PATCH,
+            other => {
+                return Err(Error::UnsupportedMediaType(format!(
+                    "{} is not supported, use {} or {}",
+                    other.unwrap_or("a missing content type"),
+                    MERGE_PATCH,
+                    JSON_PATCH
+                )))
+            }
+        };
+
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|rejection| Error::InvalidPatch(rejection.body_text()))?;
+
+        let patch = if content_type == JSON_PATCH {
+            serde_json::from_slice(&body).map(Patch::Json)
+        } else {
+            serde_json::from_slice(&body).map(Patch::Merge)
+        };
+
+        patch.map_err(|error| Error::InvalidPatch(error.to_string()))
+    }
+}
@@ -0,0 +1,375 @@
+use serde::Serialize;
+use std::marker::PhantomData;
+use wither::bson::{self, doc, Bson, Document};
+
+use crate::errors::Error;
+
+// Typed field paths and builders for filters, sorts and updates. The field
+// constants are generated with `#[derive(Fields)]`. `ModelExt` only takes the
+// filters and updates of its own model, a `doc!` converts into either of them
+// and `raw` merges one in for anything the builders don't cover.
+
+/// Path of a field `T` stored in the collection of the model `M`.
+pub struct Field<M, T> {
+    name: &'static str,
+    _marker: PhantomData<fn() -> (M, T)>,
+}
+
+impl<M, T> Field<M, T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<M, T> Clone for Field<M, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M, T> Copy for Field<M, T> {}
+
+// Values that don't serialize to BSON, such as maps with non string keys, are
+// not expected since they can't be stored in the model either. The builders
+// keep the first failure and report it when converted into a document.
+fn to_bson<T: Serialize>(value: T) -> Result<Bson, String> {
+    bson::to_bson(&value).map_err(|err| err.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct Filter<M> {
+    document: Document,
+    error: Option<String>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<M> Filter<M> {
+    pub fn new() -> Self {
+        Self::raw(Document::new())
+    }
+
+    /// Starts from a raw document, for conditions the builder doesn't support.
+    pub fn raw(document: Document) -> Self {
+        Self {
+            document,
+            error: None,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn eq<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.insert(field.name, to_bson(value.into()))
+    }
+
+    pub fn ne<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator(field.name, "$ne", to_bson(value.into()))
+    }
+
+    pub fn gt<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator(field.name, "$gt", to_bson(value.into()))
+    }
+
+    pub fn gte<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator(field.name, "$gte", to_bson(value.into()))
+    }
+
+    pub fn lt<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator(field.name, "$lt", to_bson(value.into()))
+    }
+
+    pub fn lte<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator(field.name, "$lte", to_bson(value.into()))
+    }
+
+    pub fn is_in<T, V, I>(self, field: Field<M, T>, values: I) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+        I: IntoIterator<Item = V>,
+    {
+        let values = values
+            .into_iter()
+            .map(|value| to_bson(value.into()))
+            .collect::<Result<Vec<Bson>, String>>()
+            .map(Bson::Array);
+
+        self.operator(field.name, "$in", values)
+    }
+
+    pub fn exists<T>(self, field: Field<M, T>, exists: bool) -> Self {
+        self.operator(field.name, "$exists", Ok(Bson::Boolean(exists)))
+    }
+
+    /// Adds raw conditions. Keys that are already set are combined with
+    /// `$and`, so both conditions apply instead of one replacing the other.
+    pub fn merge(mut self, document: Document) -> Self {
+        let mut both = Vec::new();
+
+        for (key, value) in document {
+            let Some(current) = self.document.remove(&key) else {
+                self.document.insert(key, value);
+                continue;
+            };
+
+            match (key.as_str(), current, value) {
+                ("$and", Bson::Array(mut current), Bson::Array(conditions)) => {
+                    current.extend(conditions);
+                    self.document.insert(key, current);
+                }
+                (_, current, value) => {
+                    both.push(Bson::Document(doc! { key.clone(): current }));
+                    both.push(Bson::Document(doc! { key: value }));
+                }
+            }
+        }
+
+        if both.is_empty() {
+            return self;
+        }
+
+        match self.document.remove("$and") {
+            Some(Bson::Array(mut conditions)) => {
+                conditions.extend(both);
+                self.document.insert("$and", conditions);
+            }
+            Some(other) => {
+                both.insert(0, Bson::Document(doc! { "$and": other }));
+                self.document.insert("$and", both);
+            }
+            None => {
+                self.document.insert("$and", both);
+            }
+        }
+
+        self
+    }
+
+    fn insert(mut self, key: &str, value: Result<Bson, String>) -> Self {
+        match value {
+            Ok(value) => {
+                self.document.insert(key, value);
+            }
+            Err(err) => {
+                self.error.get_or_insert(err);
+            }
+        }
+
+        self
+    }
+
+    // Operators on the same field are combined, e.g. `gte` and `lt` make a range.
+    fn operator(mut self, key: &str, operator: &str, value: Result<Bson, String>) -> Self {
+        let value = match value {
+            Ok(value) => value,
+            Err(err) => {
+                self.error.get_or_insert(err);
+                return self;
+            }
+        };
+
+        match self.document.get_mut(key) {
+            Some(Bson::Document(operators)) => {
+                operators.insert(operator, value);
+            }
+            _ => {
+                let mut operators = Document::new();
+                operators.insert(operator, value);
+                self.document.insert(key, operators);
+            }
+        }
+
+        self
+    }
+}
+
+impl<M> Default for Filter<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> From<Document> for Filter<M> {
+    fn from(document: Document) -> Self {
+        Self::raw(document)
+    }
+}
+
+impl<M> TryFrom<Filter<M>> for Document {
+    type Error = Error;
+
+    fn try_from(filter: Filter<M>) -> Result<Self, Self::Error> {
+        match filter.error {
+            Some(err) => Err(Error::SerializeQuery(err)),
+            None => Ok(filter.document),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sort<M> {
+    document: Document,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<M> Sort<M> {
+    pub fn new() -> Self {
+        Self {
+            document: Document::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn asc<T>(mut self, field: Field<M, T>) -> Self {
+        self.document.insert(field.name, 1_i32);
+        self
+    }
+
+    pub fn desc<T>(mut self, field: Field<M, T>) -> Self {
+        self.document.insert(field.name, -1_i32);
+        self
+    }
+}
+
+impl<M> Default for Sort<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> From<Sort<M>> for Document {
+    fn from(sort: Sort<M>) -> Self {
+        sort.document
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Update<M> {
+    document: Document,
+    error: Option<String>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<M> Update<M> {
+    pub fn new() -> Self {
+        Self::raw(Document::new())
+    }
+
+    /// Starts from a raw update document, e.g. `doc! { "$push": ... }`.
+    pub fn raw(document: Document) -> Self {
+        Self {
+            document,
+            error: None,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn set<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator("$set", field.name, to_bson(value.into()))
+    }
+
+    pub fn unset<T>(self, field: Field<M, T>) -> Self {
+        self.operator("$unset", field.name, Ok(Bson::String(String::new())))
+    }
+
+    pub fn inc<T, V>(self, field: Field<M, T>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator("$inc", field.name, to_bson(value.into()))
+    }
+
+    pub fn push<T, V>(self, field: Field<M, Vec<T>>, value: V) -> Self
+    where
+        T: Serialize,
+        V: Into<T>,
+    {
+        self.operator("$push", field.name, to_bson(value.into()))
+    }
+
+    /// Removes the elements matching `condition`, e.g. `doc! { "user": id }`.
+    pub fn pull<T>(self, field: Field<M, Vec<T>>, condition: Document) -> Self {
+        self.operator("$pull", field.name, Ok(Bson::Document(condition)))
+    }
+
+    fn operator(mut self, operator: &str, key: &str, value: Result<Bson, String>) -> Self {
+        let value = match value {
+            Ok(value) => value,
+            Err(err) => {
+                self.error.get_or_insert(err);
+                return self;
+            }
+        };
+
+        match self.document.get_mut(operator) {
+            Some(Bson::Document(fields)) => {
+                fields.insert(key, value);
+            }
+            _ => {
+                let mut fields = Document::new();
+                fields.insert(key, value);
+                self.document.insert(operator, fields);
+            }
+        }
+
+        self
+    }
+}
+
+impl<M> Default for Update<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> From<Document> for Update<M> {
+    fn from(document: Document) -> Self {
+        Self::raw(document)
+    }
+}
+
+impl<M> TryFrom<Update<M>> for Document {
+    type Error = Error;
+
+    fn try_from(update: Update<M>) -> Result<Self, Self::Error> {
+        match update.error {
+            Some(err) => Err(Error::SerializeQuery(err)),
+            None => Ok(update.document),
+        }
+    }
+}
@@ -5,3 +5,28 @@ use crate::errors::Error;
 pub fn to_object_id<S: AsRef<str>>(id: S) -> Result<ObjectId, Error> {
     ObjectId::parse_str(id.as_ref()).map_err(|_| Error::ParseObjectID(id.as_ref().to_string()))
 }
+
+/// Serde helpers for an optional id as a hex string, the `Option` counterpart
+/// of `bson::serde_helpers::serialize_object_id_as_hex_string`.
+pub mod option_hex {
+    use bson::oid::ObjectId;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        id: &Option<ObjectId>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match id {
+            Some(id) => serializer.serialize_some(&id.to_hex()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<ObjectId>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|id| ObjectId::parse_str(&id).map_err(de::Error::custom))
+            .transpose()
+    }
+}
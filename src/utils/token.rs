@@ -10,6 +10,20 @@ type TokenResult = Result<TokenData<Claims>, Error>;
 static VALIDATION: Lazy<Validation> = Lazy::new(Validation::default);
 static HEADER: Lazy<Header> = Lazy::new(Header::default);
 
+pub struct Keys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl Keys {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenUser {
     pub id: ObjectId,
@@ -44,15 +58,12 @@ impl Claims {
     }
 }
 
-pub fn create(user: User, secret: &str) -> Result<String, Error> {
-    let encoding_key = EncodingKey::from_secret(secret.as_ref());
+pub fn create(user: User, keys: &Keys) -> Result<String, Error> {
     let claims = Claims::new(user);
 
-    jsonwebtoken::encode(&HEADER, &claims, &encoding_key)
+    jsonwebtoken::encode(&HEADER, &claims, &keys.encoding)
 }
 
-pub fn decode(token: &str, secret: &str) -> TokenResult {
-    let decoding_key = DecodingKey::from_secret(secret.as_ref());
-
-    jsonwebtoken::decode::<Claims>(token, &decoding_key, &VALIDATION)
+pub fn decode(token: &str, keys: &Keys) -> TokenResult {
+    jsonwebtoken::decode::<Claims>(token, &keys.decoding, &VALIDATION)
 }